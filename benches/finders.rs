@@ -0,0 +1,84 @@
+// cargo bench, with PRIME_FINDER_BENCH_SIZE=<bytes> to change the size of the scanned buffer
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::{sized_fixture, Fixture};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use prime_finder::{
+    bigint::Integer, build_pqn_map, find_candidate_primes, find_composites, set_progress_enabled,
+    AhoCorasickOptions, Algorithm, ByteOrder, CandidateOptions, FilterUnit, NullFilter,
+};
+use std::collections::HashSet;
+
+// Large primes keep the number of candidates in random data, and so the N map, small
+const PRIME_BITS: u32 = 1024;
+const DEFAULT_SIZE: usize = 64 * 1024;
+
+fn bench_fixture() -> Fixture {
+    set_progress_enabled(false);
+    let size = std::env::var("PRIME_FINDER_BENCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_SIZE);
+    sized_fixture(PRIME_BITS, 1, size)
+}
+
+fn candidates(fixture: &Fixture) -> HashSet<Integer> {
+    let options = CandidateOptions {
+        null_filter: NullFilter {
+            length: fixture.prime_size,
+            unit: FilterUnit::Bytes,
+            byte: 0,
+        },
+        ..CandidateOptions::default()
+    };
+    find_candidate_primes(&fixture.data, fixture.prime_size, &options)
+}
+
+fn candidate_phase(c: &mut Criterion) {
+    let fixture = bench_fixture();
+    let mut group = c.benchmark_group("candidates");
+    group.throughput(Throughput::Bytes(fixture.data.len() as u64));
+    group.sample_size(10);
+    group.bench_function("find_candidate_primes", |b| b.iter(|| candidates(black_box(&fixture))));
+    group.finish();
+}
+
+fn pqn_map(c: &mut Criterion) {
+    let fixture = bench_fixture();
+    let primes = candidates(&fixture);
+    let mut group = c.benchmark_group("pqn_map");
+    group.throughput(Throughput::Elements(primes.len() as u64));
+    group.sample_size(10);
+    group.bench_function("build_pqn_map", |b| {
+        b.iter(|| build_pqn_map(black_box(&primes), false, ByteOrder::Both))
+    });
+    group.finish();
+}
+
+fn finders(c: &mut Criterion) {
+    let fixture = bench_fixture();
+    let primes = candidates(&fixture);
+    // Timing finders that miss the embedded key would be meaningless
+    assert!(primes.contains(&fixture.p) && primes.contains(&fixture.q));
+    let pqn_tuples = build_pqn_map(&primes, false, ByteOrder::Both);
+    let mut group = c.benchmark_group("find_composites");
+    group.throughput(Throughput::Bytes(fixture.data.len() as u64));
+    group.sample_size(10);
+    let ac = AhoCorasickOptions::DEFAULT;
+    for algorithm in Algorithm::ALL {
+        let matches =
+            find_composites(algorithm, ac, &pqn_tuples, &fixture.data, fixture.prime_size);
+        let offsets: Vec<_> = matches.iter().map(|&(offset, _)| offset).collect();
+        assert!(offsets.contains(&fixture.msf_offset) && offsets.contains(&fixture.lsf_offset));
+        let id = BenchmarkId::from_parameter(format!("{:?}", algorithm));
+        let data = &fixture.data;
+        group.bench_function(id, |b| {
+            b.iter(|| find_composites(algorithm, ac, &pqn_tuples, black_box(data), fixture.prime_size))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, candidate_phase, pqn_map, finders);
+criterion_main!(benches);
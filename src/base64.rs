@@ -0,0 +1,95 @@
+use ::base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+
+/// A run of base64 text in a buffer, and the bytes it decodes to
+pub struct Base64Run {
+    pub text_offset: usize,
+    pub decoded: Vec<u8>,
+}
+
+fn decode_value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
+
+/// Encodes `data` with the standard alphabet and padding
+pub fn encode(data: &[u8]) -> String {
+    STANDARD.encode(data)
+}
+
+/// Encodes `data` with the URL and filename safe alphabet and no padding, as used by JWK
+pub fn encode_url(data: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(data)
+}
+
+// Decodes as many whole bytes as possible, ignoring line breaks and padding
+fn decode(text: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for value in text.iter().filter_map(|&c| decode_value(c)) {
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    decoded
+}
+
+/// Decodes the contents of every PEM block in `data`, e.g. the certificates of a bundle
+pub fn decode_pem(data: &[u8]) -> Vec<Vec<u8>> {
+    let text = String::from_utf8_lossy(data);
+    text.split("-----BEGIN ")
+        .skip(1)
+        // The rest of the BEGIN line is the label, the base64 text ends at the END line
+        .filter_map(|block| block.split_once('\n'))
+        .map(|(_, body)| decode(body.split("-----END").next().unwrap().as_bytes()))
+        .collect()
+}
+
+/// Finds all runs of base64 text in `data` decoding to at least `min_decoded_length` bytes
+///
+/// Runs may span line breaks, as in PEM files, and end at padding or any other character.
+/// Where the encoded data starts in a run is unknown, so every run is decoded from each of
+/// its first four characters.
+pub fn find_runs(data: &[u8], min_decoded_length: usize) -> Vec<Base64Run> {
+    let mut runs = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        if decode_value(data[offset]).is_none() {
+            offset += 1;
+            continue;
+        }
+
+        let run_start = offset;
+        while offset < data.len()
+            && (decode_value(data[offset]).is_some() || matches!(data[offset], b'\r' | b'\n'))
+        {
+            offset += 1;
+        }
+
+        let alignments = (run_start..offset)
+            .filter(|&i| decode_value(data[i]).is_some())
+            .take(4);
+        for text_offset in alignments {
+            let decoded = decode(&data[text_offset..offset]);
+            if decoded.len() >= min_decoded_length {
+                runs.push(Base64Run {
+                    text_offset,
+                    decoded,
+                });
+            }
+        }
+    }
+    runs
+}
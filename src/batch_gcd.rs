@@ -0,0 +1,69 @@
+use crate::bigint::Integer;
+use rayon::{
+    iter::IndexedParallelIterator, iter::IntoParallelRefIterator, iter::ParallelIterator,
+    slice::ParallelSlice,
+};
+
+fn product_tree(values: &[Integer]) -> Vec<Vec<Integer>> {
+    let mut tree = vec![values.to_vec()];
+    while tree.last().unwrap().len() > 1 {
+        let level = tree
+            .last()
+            .unwrap()
+            .par_chunks(2)
+            .map(|pair| match pair {
+                [a, b] => Integer::from(a * b),
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        tree.push(level);
+    }
+    tree
+}
+
+/// Computes the GCD of every value with the product of all the other values
+pub fn batch_gcd(values: &[Integer]) -> Vec<Integer> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+
+    // Walk down the product tree, reducing the total product modulo the square of every node
+    let mut tree = product_tree(values);
+    let mut remainders = tree.pop().unwrap();
+    while let Some(level) = tree.pop() {
+        remainders = level
+            .par_iter()
+            .enumerate()
+            .map(|(i, x)| &remainders[i / 2] % Integer::from(x.square_ref()))
+            .collect();
+    }
+
+    values
+        .par_iter()
+        .zip(remainders)
+        .map(|(x, remainder)| (remainder / x).gcd(x))
+        .collect()
+}
+
+/// Finds all pairs of values sharing a nontrivial factor, as (index, index, GCD)
+pub fn shared_factors(values: &[Integer]) -> Vec<(usize, usize, Integer)> {
+    let vulnerable: Vec<usize> = batch_gcd(values)
+        .iter()
+        .enumerate()
+        .filter(|(_, gcd)| **gcd != 1)
+        .map(|(i, _)| i)
+        .collect();
+
+    // Only the few values with a common factor need to be compared pairwise
+    let mut pairs = Vec::new();
+    for (k, &i) in vulnerable.iter().enumerate() {
+        for &j in &vulnerable[k + 1..] {
+            let gcd = Integer::from(values[i].gcd_ref(&values[j]));
+            if gcd != 1 {
+                pairs.push((i, j, gcd));
+            }
+        }
+    }
+    pairs
+}
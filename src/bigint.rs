@@ -0,0 +1,20 @@
+//! The big integer backend
+//!
+//! GMP through rug is used by default, as it is much faster for the primality tests that
+//! dominate a scan. Building with `--no-default-features --features pure-rust` swaps in
+//! num-bigint and num-prime for environments where GMP can't be linked. Both backends are
+//! used through the `Integer`, `Order` and `IsPrime` exported here, which follow rug's API.
+
+#[cfg(not(any(feature = "rug", feature = "pure-rust")))]
+compile_error!("Either the rug or the pure-rust feature must be enabled");
+
+#[cfg(not(feature = "pure-rust"))]
+pub use rug::{
+    integer::{IsPrime, Order},
+    Integer,
+};
+
+#[cfg(feature = "pure-rust")]
+mod pure_rust;
+#[cfg(feature = "pure-rust")]
+pub use pure_rust::{Digit, Incomplete, Integer, IsPrime, Order};
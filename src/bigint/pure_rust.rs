@@ -0,0 +1,321 @@
+//! The subset of rug's `Integer` used by the crate, implemented on num-bigint
+
+use num_bigint::{BigInt, ParseBigIntError, Sign};
+use num_integer::Integer as _;
+use num_prime::{nt_funcs::is_prime, Primality, PrimalityTestConfig};
+use num_traits::{Num, One, Signed, ToPrimitive, Zero};
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Div, Mul, Rem, Shl, Shr, Sub},
+};
+
+/// The order of the digits in `Integer::from_digits` and `Integer::to_digits`
+///
+/// Only bytes are supported as digits, so the endianness of the digits themselves is ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Order {
+    Lsf,
+    LsfLe,
+    LsfBe,
+    Msf,
+    MsfLe,
+    MsfBe,
+}
+
+impl Order {
+    fn msf(self) -> bool {
+        matches!(self, Order::Msf | Order::MsfLe | Order::MsfBe)
+    }
+}
+
+/// The result of a primality test
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IsPrime {
+    No,
+    Probably,
+    Yes,
+}
+
+/// A digit type of `Integer::from_digits` and `Integer::to_digits`, only bytes are supported
+pub trait Digit: Copy {
+    fn from_bytes(bytes: Vec<u8>) -> Vec<Self>;
+    fn as_bytes(digits: &[Self]) -> &[u8];
+}
+
+impl Digit for u8 {
+    fn from_bytes(bytes: Vec<u8>) -> Vec<u8> {
+        bytes
+    }
+
+    fn as_bytes(digits: &[u8]) -> &[u8] {
+        digits
+    }
+}
+
+/// An arbitrary precision integer
+///
+/// Arithmetic on references returns an [`Incomplete`] like in rug, so `Integer::from(&a * &b)`
+/// is needed and valid with both backends.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Integer(BigInt);
+
+/// The result of arithmetic on a reference, which becomes an `Integer` with `Integer::from`
+pub struct Incomplete(BigInt);
+
+impl From<Incomplete> for Integer {
+    fn from(value: Incomplete) -> Integer {
+        Integer(value.0)
+    }
+}
+
+impl Integer {
+    pub fn new() -> Integer {
+        Integer(BigInt::zero())
+    }
+
+    pub fn from_digits<T: Digit>(digits: &[T], order: Order) -> Integer {
+        let bytes = T::as_bytes(digits);
+        Integer(if order.msf() {
+            BigInt::from_bytes_be(Sign::Plus, bytes)
+        } else {
+            BigInt::from_bytes_le(Sign::Plus, bytes)
+        })
+    }
+
+    pub fn assign_digits<T: Digit>(&mut self, digits: &[T], order: Order) {
+        *self = Integer::from_digits(digits, order);
+    }
+
+    /// Returns the digits of the absolute value, which are empty for zero like in rug
+    pub fn to_digits<T: Digit>(&self, order: Order) -> Vec<T> {
+        if self.0.is_zero() {
+            return Vec::new();
+        }
+        let magnitude = self.0.magnitude();
+        T::from_bytes(if order.msf() {
+            magnitude.to_bytes_be()
+        } else {
+            magnitude.to_bytes_le()
+        })
+    }
+
+    pub fn from_str_radix(src: &str, radix: i32) -> Result<Integer, ParseBigIntError> {
+        BigInt::from_str_radix(src, radix as u32).map(Integer)
+    }
+
+    pub fn significant_bits(&self) -> u32 {
+        self.0.bits() as u32
+    }
+
+    pub fn set_bit(&mut self, index: u32, val: bool) -> &mut Integer {
+        self.0.set_bit(index.into(), val);
+        self
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.0.is_even()
+    }
+
+    pub fn is_divisible(&self, divisor: &Integer) -> bool {
+        if divisor.0.is_zero() {
+            return self.0.is_zero();
+        }
+        (&self.0 % &divisor.0).is_zero()
+    }
+
+    pub fn is_divisible_u(&self, divisor: u32) -> bool {
+        if divisor == 0 {
+            return self.0.is_zero();
+        }
+        (&self.0 % divisor).is_zero()
+    }
+
+    pub fn to_u32(&self) -> Option<u32> {
+        self.0.to_u32()
+    }
+
+    pub fn to_u32_wrapping(&self) -> u32 {
+        let low = self.0.iter_u32_digits().next().unwrap_or(0);
+        match self.0.sign() {
+            Sign::Minus => low.wrapping_neg(),
+            _ => low,
+        }
+    }
+
+    pub fn abs(self) -> Integer {
+        Integer(self.0.abs())
+    }
+
+    pub fn square_ref(&self) -> Incomplete {
+        Incomplete(&self.0 * &self.0)
+    }
+
+    pub fn gcd(self, other: &Integer) -> Integer {
+        Integer::from(self.gcd_ref(other))
+    }
+
+    pub fn gcd_ref(&self, other: &Integer) -> Incomplete {
+        Incomplete(self.0.gcd(&other.0))
+    }
+
+    pub fn lcm(self, other: &Integer) -> Integer {
+        Integer(self.0.lcm(&other.0))
+    }
+
+    /// Returns the inverse modulo `modulus`, or gives back `self` when there is none
+    pub fn invert(self, modulus: &Integer) -> Result<Integer, Integer> {
+        if modulus.0.is_zero() {
+            return Err(self);
+        }
+        match self.0.modinv(&modulus.0) {
+            Some(inverse) => Ok(Integer(inverse)),
+            None => Err(self),
+        }
+    }
+
+    /// Returns `self` to the power of `exponent` modulo `modulus`, or gives back `self` when
+    /// the exponent is negative and `self` has no inverse
+    pub fn pow_mod(self, exponent: &Integer, modulus: &Integer) -> Result<Integer, Integer> {
+        if modulus.0.is_zero() {
+            return Err(self);
+        }
+        if exponent.0.is_negative() {
+            let inverse = self.invert(modulus)?;
+            return inverse.pow_mod(&Integer(-&exponent.0), modulus);
+        }
+        Ok(Integer(self.0.modpow(&exponent.0, &modulus.0)))
+    }
+
+    /// Tests the absolute value like GMP 6.2 does, with a Baillie-PSW test followed by
+    /// `reps - 24` Miller-Rabin rounds to random bases
+    pub fn is_probably_prime(&self, reps: u32) -> IsPrime {
+        let mut config = PrimalityTestConfig::bpsw();
+        config.sprp_random_trials = reps.saturating_sub(24) as usize;
+        match is_prime(self.0.magnitude(), Some(config)) {
+            Primality::Yes => IsPrime::Yes,
+            Primality::No => IsPrime::No,
+            Primality::Probable(_) => IsPrime::Probably,
+        }
+    }
+
+    pub fn next_prime(self) -> Integer {
+        let mut candidate = Integer(self.0.max(BigInt::one()) + 1);
+        // The rounds GMP's mpz_nextprime tests with
+        while candidate.is_probably_prime(25) == IsPrime::No {
+            candidate.0 += 1;
+        }
+        candidate
+    }
+}
+
+// rug prints integers the same way for {:?} and {}
+impl fmt::Debug for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::LowerHex for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+macro_rules! integer_ops {
+    ($($trait:ident $method:ident),*) => {$(
+        impl $trait<Integer> for Integer {
+            type Output = Integer;
+            fn $method(self, rhs: Integer) -> Integer {
+                Integer(self.0.$method(rhs.0))
+            }
+        }
+
+        impl $trait<&Integer> for Integer {
+            type Output = Integer;
+            fn $method(self, rhs: &Integer) -> Integer {
+                Integer(self.0.$method(&rhs.0))
+            }
+        }
+
+        impl $trait<Integer> for &Integer {
+            type Output = Integer;
+            fn $method(self, rhs: Integer) -> Integer {
+                Integer((&self.0).$method(rhs.0))
+            }
+        }
+
+        impl $trait<&Integer> for &Integer {
+            type Output = Incomplete;
+            fn $method(self, rhs: &Integer) -> Incomplete {
+                Incomplete((&self.0).$method(&rhs.0))
+            }
+        }
+    )*};
+}
+
+integer_ops!(Add add, Sub sub, Mul mul, Div div, Rem rem);
+
+macro_rules! primitive_ops {
+    ($primitive:ty, $($trait:ident $method:ident),*) => {$(
+        impl $trait<$primitive> for Integer {
+            type Output = Integer;
+            fn $method(self, rhs: $primitive) -> Integer {
+                Integer(self.0.$method(rhs))
+            }
+        }
+
+        impl $trait<$primitive> for &Integer {
+            type Output = Incomplete;
+            fn $method(self, rhs: $primitive) -> Incomplete {
+                Incomplete((&self.0).$method(rhs))
+            }
+        }
+    )*};
+}
+
+macro_rules! primitive {
+    ($($primitive:ty),*) => {$(
+        primitive_ops!($primitive, Add add, Sub sub, Mul mul);
+
+        impl From<$primitive> for Integer {
+            fn from(value: $primitive) -> Integer {
+                Integer(BigInt::from(value))
+            }
+        }
+
+        impl PartialEq<$primitive> for Integer {
+            fn eq(&self, other: &$primitive) -> bool {
+                self.0 == BigInt::from(*other)
+            }
+        }
+
+        impl PartialOrd<$primitive> for Integer {
+            fn partial_cmp(&self, other: &$primitive) -> Option<Ordering> {
+                self.0.partial_cmp(&BigInt::from(*other))
+            }
+        }
+    )*};
+}
+
+primitive!(i32, u32);
+
+impl Shl<u32> for Integer {
+    type Output = Integer;
+    fn shl(self, bits: u32) -> Integer {
+        Integer(self.0 << bits)
+    }
+}
+
+impl Shr<u32> for Integer {
+    type Output = Integer;
+    fn shr(self, bits: u32) -> Integer {
+        Integer(self.0 >> bits)
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    bigint::{Integer, IsPrime, Order},
+    key::RsaKey,
+    progress_bar,
+};
+use indicatif::ParallelProgressIterator;
+use log::info;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{collections::HashSet, convert::TryInto};
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_NULL: u8 = 0x05;
+const TAG_SEQUENCE: u8 = 0x30;
+
+// The encoded OBJECT IDENTIFIERs of rsaEncryption (1.2.840.113549.1.1.1) and commonName (2.5.4.3)
+const OID_RSA_ENCRYPTION: &[u8] = &[0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_COMMON_NAME: &[u8] = &[0x06, 0x03, 0x55, 0x04, 0x03];
+
+/// An RSA public key found in a certificate or SubjectPublicKeyInfo
+pub struct PublicKey {
+    pub offset: usize,
+    pub n: Integer,
+    pub e: Integer,
+    /// The last common name before the key, which for a certificate is its subject
+    pub common_name: Option<String>,
+}
+
+/// Parses a DER tag and length at the start of `data`, returning the tag and its contents
+pub fn parse_tlv(data: &[u8]) -> Option<(u8, &[u8])> {
+    parse_tlv_with_rest(data).map(|(tag, contents, _)| (tag, contents))
+}
+
+/// Returns the length of the DER element at the start of `data`, header included
+pub fn tlv_length(data: &[u8]) -> Option<usize> {
+    parse_tlv_with_rest(data).map(|(_, _, rest)| data.len() - rest.len())
+}
+
+// Like parse_tlv, also returning the data following the contents
+fn parse_tlv_with_rest(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let (length, header) = match *data.get(1)? {
+        length if length < 0x80 => (length as usize, 2),
+        0x81 => (*data.get(2)? as usize, 3),
+        0x82 => (u16::from_be_bytes([*data.get(2)?, *data.get(3)?]) as usize, 4),
+        _ => return None,
+    };
+    let contents = data.get(header..header + length)?;
+    Some((tag, contents, &data[header + length..]))
+}
+
+/// Finds probable primes encoded as DER INTEGERs of roughly `prime_size` bytes
+///
+/// The length comes from the DER header rather than a fixed window, so primes are found
+/// even when surrounding data has been shifted or partially overwritten.
+pub fn find_der_primes(data: &[u8], prime_size: usize, mr_rounds: u32) -> HashSet<Integer> {
+    let tolerance = (prime_size / 8).max(1);
+    let lengths = prime_size.saturating_sub(tolerance)..=prime_size + tolerance;
+
+    let pb = progress_bar(data.len().try_into().unwrap());
+
+    info!("Finding DER encoded candidate primes");
+    (0..data.len())
+        .into_par_iter()
+        .progress_with(pb)
+        .filter(|&offset| data[offset] == TAG_INTEGER)
+        .filter_map(|offset| match parse_tlv(&data[offset..]) {
+            Some((TAG_INTEGER, contents)) => Some(contents),
+            _ => None,
+        })
+        .map(|contents| {
+            // Positive integers have a leading zero byte when the high bit is set
+            let skip = contents.iter().take_while(|&&b| b == 0).count();
+            &contents[skip..]
+        })
+        .filter(|contents| lengths.contains(&contents.len()))
+        .map(|contents| Integer::from_digits(contents, Order::Msf))
+        .filter(|number| match number.is_probably_prime(mr_rounds) {
+            IsPrime::Yes | IsPrime::Probably => true,
+            IsPrime::No => false,
+        })
+        .collect()
+}
+
+// Parses an RSAPrivateKey (RFC 8017 A.1.2), checking that its values belong together
+fn parse_private_key(data: &[u8]) -> Option<RsaKey> {
+    let (tag, mut contents) = parse_tlv(data)?;
+    if tag != TAG_SEQUENCE {
+        return None;
+    }
+    let mut values = Vec::with_capacity(9);
+    for _ in 0..9 {
+        let (tag, value, rest) = parse_tlv_with_rest(contents)?;
+        if tag != TAG_INTEGER {
+            return None;
+        }
+        values.push(Integer::from_digits(value, Order::Msf));
+        contents = rest;
+    }
+    let [version, n, e, d, p, q, dp, dq, qinv]: [Integer; 9] = values.try_into().ok()?;
+    if version != 0 || p <= 1 || q <= 1 || !n.is_divisible(&p) || Integer::from(&n / &p) != q {
+        return None;
+    }
+    // e*d is 1 modulo lcm(p-1, q-1), whether d was computed modulo phi or lambda
+    let lambda = Integer::from(&p - 1).lcm(&Integer::from(&q - 1));
+    if Integer::from(&e * &d) % &lambda != 1 {
+        return None;
+    }
+    Some(RsaKey {
+        n,
+        e,
+        d,
+        p,
+        q,
+        dp,
+        dq,
+        qinv,
+    })
+}
+
+/// Finds RSA private keys stored as DER RSAPrivateKey structures, along with their offsets
+///
+/// Unlike the prime search, this relies on the surrounding structure being intact, but
+/// finds keys of any size and recovers the stored exponents.
+pub fn find_der_keys(data: &[u8]) -> Vec<(usize, RsaKey)> {
+    let pb = progress_bar(data.len().try_into().unwrap());
+
+    info!("Finding DER encoded private keys");
+    let mut keys: Vec<_> = (0..data.len())
+        .into_par_iter()
+        .progress_with(pb)
+        .filter(|&offset| data[offset] == TAG_SEQUENCE)
+        .filter_map(|offset| parse_private_key(&data[offset..]).map(|key| (offset, key)))
+        .collect();
+    keys.sort_by_key(|&(offset, _)| offset);
+    keys
+}
+
+// Parses the parameters and key of a SubjectPublicKeyInfo following its rsaEncryption OID
+fn parse_rsa_public_key(data: &[u8]) -> Option<(Integer, Integer)> {
+    let data = match data {
+        [TAG_NULL, 0, rest @ ..] => rest,
+        _ => data,
+    };
+    // The RSAPublicKey (RFC 8017 A.1.1) is a whole number of bytes, so no bits are unused
+    let key = match parse_tlv(data)? {
+        (TAG_BIT_STRING, [0, key @ ..]) => key,
+        _ => return None,
+    };
+    let (tag, contents) = parse_tlv(key)?;
+    if tag != TAG_SEQUENCE {
+        return None;
+    }
+    let (n_tag, n, rest) = parse_tlv_with_rest(contents)?;
+    let (e_tag, e, _) = parse_tlv_with_rest(rest)?;
+    if n_tag != TAG_INTEGER || e_tag != TAG_INTEGER {
+        return None;
+    }
+    Some((
+        Integer::from_digits(n, Order::Msf),
+        Integer::from_digits(e, Order::Msf),
+    ))
+}
+
+// The value of the last commonName attribute in `data`
+fn last_common_name(data: &[u8]) -> Option<String> {
+    let start = data
+        .windows(OID_COMMON_NAME.len())
+        .rposition(|window| window == OID_COMMON_NAME)?;
+    let (_, value) = parse_tlv(&data[start + OID_COMMON_NAME.len()..])?;
+    Some(String::from_utf8_lossy(value).into_owned())
+}
+
+/// Finds the RSA public keys of DER encoded X.509 certificates or SubjectPublicKeyInfos
+///
+/// Certificates are not parsed as a whole, every rsaEncryption algorithm identifier is
+/// followed to the key after it. Bare PKCS#1 RSAPublicKeys have no identifier and are missed.
+pub fn find_public_keys(data: &[u8]) -> Vec<PublicKey> {
+    let mut keys = Vec::new();
+    // The subject of a certificate is the last name before its key, after the previous key
+    let mut names_start = 0;
+    for (offset, window) in data.windows(OID_RSA_ENCRYPTION.len()).enumerate() {
+        if window != OID_RSA_ENCRYPTION {
+            continue;
+        }
+        if let Some((n, e)) = parse_rsa_public_key(&data[offset + OID_RSA_ENCRYPTION.len()..]) {
+            keys.push(PublicKey {
+                offset,
+                n,
+                e,
+                common_name: last_common_name(&data[names_start..offset]),
+            });
+            names_start = offset;
+        }
+    }
+    keys
+}
@@ -0,0 +1,24 @@
+use crate::MAX_PRIME_SIZE;
+use thiserror::Error;
+
+/// The errors returned by the library, and printed by the command line tool
+#[derive(Debug, Error)]
+pub enum FinderError {
+    #[error("File is smaller than prime size ({length} < {prime_size} bytes)")]
+    FileTooSmall { length: usize, prime_size: usize },
+    #[error("Invalid prime size of {0} bytes, RSA primes are between 2 and {} bytes", MAX_PRIME_SIZE)]
+    InvalidPrimeSize(usize),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Invalid value {value} for --{arg}: {message}")]
+    ParseArg {
+        arg: String,
+        value: String,
+        message: String,
+    },
+    #[error("{value} is not invertible modulo {modulus}")]
+    NoInverse {
+        value: String,
+        modulus: &'static str,
+    },
+}
@@ -0,0 +1,194 @@
+use crate::{
+    base64,
+    bigint::{Integer, Order},
+    error::FinderError,
+};
+use serde::Serialize;
+
+pub const DEFAULT_EXPONENT: u32 = 65537;
+
+pub struct RsaKey {
+    pub n: Integer,
+    pub e: Integer,
+    pub d: Integer,
+    pub p: Integer,
+    pub q: Integer,
+    pub dp: Integer,
+    pub dq: Integer,
+    pub qinv: Integer,
+}
+
+impl RsaKey {
+    // Fails if e has no inverse modulo phi, i.e. gcd(e, phi) != 1
+    pub fn from_primes(p: &Integer, q: &Integer, e: u32) -> Result<RsaKey, FinderError> {
+        let n = Integer::from(p * q);
+        let p1 = Integer::from(p - 1);
+        let q1 = Integer::from(q - 1);
+        let phi = Integer::from(&p1 * &q1);
+        let e = Integer::from(e);
+        let d = e.clone().invert(&phi).map_err(|_| FinderError::NoInverse {
+            value: format!("e={}", e),
+            modulus: "phi",
+        })?;
+        let dp = Integer::from(&d % &p1);
+        let dq = Integer::from(&d % &q1);
+        let qinv = q.clone().invert(p).map_err(|_| FinderError::NoInverse {
+            value: "Q".to_string(),
+            modulus: "P",
+        })?;
+
+        Ok(RsaKey {
+            n,
+            e,
+            d,
+            p: p.clone(),
+            q: q.clone(),
+            dp,
+            dq,
+            qinv,
+        })
+    }
+
+    // RSAPrivateKey as defined in PKCS#1 (RFC 8017 A.1.2)
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for value in [
+            &Integer::new(),
+            &self.n,
+            &self.e,
+            &self.d,
+            &self.p,
+            &self.q,
+            &self.dp,
+            &self.dq,
+            &self.qinv,
+        ] {
+            der_integer(&mut body, value);
+        }
+
+        let mut der = Vec::with_capacity(body.len() + 4);
+        der.push(0x30);
+        der_length(&mut der, body.len());
+        der.extend_from_slice(&body);
+        der
+    }
+
+    pub fn to_pem(&self) -> String {
+        armor("RSA PRIVATE KEY", &self.to_der(), 64)
+    }
+
+    // Unencrypted openssh-key-v1 as written by ssh-keygen (PROTOCOL.key in OpenSSH)
+    pub fn to_openssh(&self, comment: &str) -> String {
+        let mut public = Vec::new();
+        ssh_string(&mut public, b"ssh-rsa");
+        ssh_mpint(&mut public, &self.e);
+        ssh_mpint(&mut public, &self.n);
+
+        // The two check integers only have to match, they detect a wrong passphrase
+        let check = self.n.to_u32_wrapping().to_be_bytes();
+        let mut private = Vec::new();
+        private.extend_from_slice(&check);
+        private.extend_from_slice(&check);
+        ssh_string(&mut private, b"ssh-rsa");
+        for value in [&self.n, &self.e, &self.d, &self.qinv, &self.p, &self.q] {
+            ssh_mpint(&mut private, value);
+        }
+        ssh_string(&mut private, comment.as_bytes());
+        // Padded to the block size of the cipher, which is 8 without one
+        let mut padding = 1;
+        while !private.len().is_multiple_of(8) {
+            private.push(padding);
+            padding += 1;
+        }
+
+        let mut blob = b"openssh-key-v1\0".to_vec();
+        ssh_string(&mut blob, b"none");
+        ssh_string(&mut blob, b"none");
+        ssh_string(&mut blob, b"");
+        blob.extend_from_slice(&1u32.to_be_bytes());
+        ssh_string(&mut blob, &public);
+        ssh_string(&mut blob, &private);
+        armor("OPENSSH PRIVATE KEY", &blob, 70)
+    }
+
+    // A single line JSON object, so every key is one line of output
+    pub fn to_jwk(&self) -> String {
+        let jwk = Jwk {
+            kty: "RSA",
+            n: jwk_integer(&self.n),
+            e: jwk_integer(&self.e),
+            d: jwk_integer(&self.d),
+            p: jwk_integer(&self.p),
+            q: jwk_integer(&self.q),
+            dp: jwk_integer(&self.dp),
+            dq: jwk_integer(&self.dq),
+            qi: jwk_integer(&self.qinv),
+        };
+        serde_json::to_string(&jwk).unwrap()
+    }
+}
+
+// A private RSA JSON Web Key (RFC 7518 6.3), every value as unsigned big endian base64url
+#[derive(Serialize)]
+struct Jwk {
+    kty: &'static str,
+    n: String,
+    e: String,
+    d: String,
+    p: String,
+    q: String,
+    dp: String,
+    dq: String,
+    qi: String,
+}
+
+fn armor(label: &str, data: &[u8], line_length: usize) -> String {
+    let encoded = base64::encode(data);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(line_length) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+fn jwk_integer(value: &Integer) -> String {
+    base64::encode_url(&value.to_digits::<u8>(Order::Msf))
+}
+
+fn ssh_string(out: &mut Vec<u8>, value: &[u8]) {
+    out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+// Like der_integer, positive values need a leading zero byte if the high bit is set
+fn ssh_mpint(out: &mut Vec<u8>, value: &Integer) {
+    let mut digits = value.to_digits::<u8>(Order::Msf);
+    if digits.first().is_some_and(|&b| b & 0x80 != 0) {
+        digits.insert(0, 0);
+    }
+    ssh_string(out, &digits);
+}
+
+fn der_length(out: &mut Vec<u8>, length: usize) {
+    if length < 0x80 {
+        out.push(length as u8);
+    } else {
+        let bytes = length.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+}
+
+fn der_integer(out: &mut Vec<u8>, value: &Integer) {
+    let mut digits = value.to_digits::<u8>(Order::Msf);
+    // Positive integers need a leading zero byte if the high bit is set
+    if digits.first().is_none_or(|&b| b & 0x80 != 0) {
+        digits.insert(0, 0);
+    }
+    out.push(0x02);
+    der_length(out, digits.len());
+    out.extend_from_slice(&digits);
+}
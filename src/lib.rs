@@ -0,0 +1,1525 @@
+pub mod base64;
+pub mod batch_gcd;
+pub mod bigint;
+pub mod der;
+pub mod error;
+pub mod key;
+pub mod montgomery;
+pub mod numeric;
+pub mod pollard_rho;
+
+use aho_corasick::AhoCorasickBuilder;
+use bigint::{Integer, IsPrime, Order};
+use error::FinderError;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+use indicatif::{ParallelProgressIterator, ProgressIterator};
+use log::{debug, info, trace};
+use memchr::memchr;
+use rayon::{
+    iter::IndexedParallelIterator, iter::IntoParallelIterator, iter::IntoParallelRefIterator,
+    iter::IntoParallelRefMutIterator, iter::ParallelIterator, slice::ParallelSlice,
+    slice::ParallelSliceMut,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    collections::HashSet,
+    convert::TryInto,
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    sync::Mutex,
+};
+
+// Candidates divisible by any prime below this are rejected before running Miller-Rabin
+// ./target/release/prime-finder -f 2 -s 64 -p 500KB-of-urandom  8.79s without, 7.44s with trial division
+const TRIAL_DIVISION_LIMIT: u32 = 2_000;
+
+// Multiplier of the polynomial rolling hash used by finder_rabin_karp
+const RABIN_KARP_BASE: u64 = 0x100000001b3;
+
+// Depth of the NFA states with dense transitions for --ac-dense, the aho-corasick default
+const AHO_CORASICK_DENSE_DEPTH: usize = 2;
+
+/// The largest supported prime size in bytes, so the bit length of N still fits in a u32
+pub const MAX_PRIME_SIZE: usize = (u32::MAX / 16) as usize;
+
+/// The default number of times per second progress bars are redrawn
+pub const DEFAULT_PROGRESS_RATE: u64 = 4;
+
+static PROGRESS_ENABLED: AtomicBool = AtomicBool::new(true);
+static PROGRESS_RATE: AtomicU64 = AtomicU64::new(DEFAULT_PROGRESS_RATE);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // The bar set by with_progress_bar for the file scanned on this thread
+    static FILE_PROGRESS_BAR: RefCell<Option<ProgressBar>> = const { RefCell::new(None) };
+}
+
+/// Enables or disables the progress bars drawn by all search functions
+pub fn set_progress_enabled(enabled: bool) {
+    PROGRESS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Sets the number of times per second progress bars are redrawn
+pub fn set_progress_rate(hz: u64) {
+    PROGRESS_RATE.store(hz, Ordering::Relaxed);
+}
+
+/// How the automaton of the aho-corasick finder is built, trading search speed for memory
+#[derive(Debug, Clone, Copy)]
+pub struct AhoCorasickOptions {
+    /// Builds a DFA, which searches 2-4 times faster than the NFA but uses far more memory
+    pub dfa: bool,
+    /// Uses dense transitions for the NFA states near the start, which is faster but larger
+    pub dense: bool,
+    /// Maps the bytes to equivalence classes, which shrinks a DFA at a small cost per byte
+    pub byte_classes: bool,
+    /// Premultiplies the DFA state identifiers, which is faster but can need larger ones
+    pub premultiply: bool,
+}
+
+impl AhoCorasickOptions {
+    /// The most memory frugal configuration, to avoid running out of memory on many candidates
+    pub const DEFAULT: AhoCorasickOptions = AhoCorasickOptions {
+        dfa: false,
+        dense: false,
+        byte_classes: true,
+        premultiply: true,
+    };
+}
+
+impl Default for AhoCorasickOptions {
+    fn default() -> Self {
+        AhoCorasickOptions::DEFAULT
+    }
+}
+
+/// Makes all running and future searches skip their remaining work, e.g. on Ctrl-C
+///
+/// Searches return what they found before the interrupt.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether [`interrupt`] has been called
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Creates a `MultiProgress` drawn like the progress bars of the search functions
+///
+/// It is hidden when progress bars are disabled, and like every `MultiProgress` of indicatif
+/// 0.16 nothing is drawn until `join` is called.
+pub fn multi_progress() -> MultiProgress {
+    if !PROGRESS_ENABLED.load(Ordering::Relaxed) {
+        return MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+    }
+    let rate = PROGRESS_RATE.load(Ordering::Relaxed);
+    MultiProgress::with_draw_target(ProgressDrawTarget::stderr_with_hz(rate))
+}
+
+/// Runs `scan` with the progress of every search function it calls drawn on `bar`
+///
+/// Every search resets the bar to its own length. This lets files scanned concurrently on the
+/// rayon pool each show their progress on a bar of a `MultiProgress`, instead of all drawing
+/// on stderr at once.
+pub fn with_progress_bar<R>(bar: &ProgressBar, scan: impl FnOnce() -> R) -> R {
+    let previous = FILE_PROGRESS_BAR.with(|current| current.replace(Some(bar.clone())));
+    let result = scan();
+    FILE_PROGRESS_BAR.with(|current| current.replace(previous));
+    result
+}
+
+// Progress is drawn on stderr so that only results go to stdout
+pub(crate) fn progress_bar(length: u64) -> ProgressBar {
+    if !PROGRESS_ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    if let Some(bar) = FILE_PROGRESS_BAR.with(|current| current.borrow().clone()) {
+        bar.reset();
+        bar.set_length(length);
+        return bar;
+    }
+    let pb = ProgressBar::with_draw_target(length, ProgressDrawTarget::stderr());
+    pb.set_draw_rate(PROGRESS_RATE.load(Ordering::Relaxed));
+    pb
+}
+
+/// Maps the byte encodings of every candidate N to the primes P and Q producing it
+pub type PqnMap<'a> = HashMap<Vec<u8>, (&'a Integer, &'a Integer)>;
+
+/// Maps every candidate prime to the sorted offsets in the searched data where it was found
+pub type CandidateOffsets = HashMap<Integer, Vec<usize>>;
+
+/// The offset in the searched data where an N was found, and its P and Q
+pub type Match<'a> = (usize, &'a (&'a Integer, &'a Integer));
+
+#[derive(Debug, Clone, Copy)]
+pub enum Algorithm {
+    SlidingWindow,
+    AhoCorasick,
+    RabinKarp,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 3] = [
+        Algorithm::SlidingWindow,
+        Algorithm::AhoCorasick,
+        Algorithm::RabinKarp,
+    ];
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sliding" => Ok(Algorithm::SlidingWindow),
+            "aho-corasick" => Ok(Algorithm::AhoCorasick),
+            "rabin-karp" => Ok(Algorithm::RabinKarp),
+            _ => Err(format!("Unknown algorithm: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum FilterUnit {
+    Bytes,
+    Bits,
+}
+
+impl FromStr for FilterUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(FilterUnit::Bytes),
+            "bits" => Ok(FilterUnit::Bits),
+            _ => Err(format!("Unknown null filter unit: {}", s)),
+        }
+    }
+}
+
+/// The byte orders primes and their N are read in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    Msf,
+    Lsf,
+    Both,
+}
+
+impl ByteOrder {
+    /// Whether numbers stored in `order` are read, limbs count as the order of their bytes
+    pub fn includes(&self, order: Order) -> bool {
+        matches!(
+            (self, order),
+            (ByteOrder::Both, _) | (ByteOrder::Msf, Order::Msf) | (ByteOrder::Lsf, Order::Lsf)
+        )
+    }
+}
+
+impl FromStr for ByteOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "msf" => Ok(ByteOrder::Msf),
+            "lsf" => Ok(ByteOrder::Lsf),
+            "both" => Ok(ByteOrder::Both),
+            _ => Err(format!("Unknown byte order: {}", s)),
+        }
+    }
+}
+
+/// A per byte transform undoing how some layouts store bytes, applied before the search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitTransform {
+    None,
+    ReverseBits,
+    SwapNibbles,
+}
+
+impl FromStr for BitTransform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(BitTransform::None),
+            "reverse-bits" => Ok(BitTransform::ReverseBits),
+            "swap-nibbles" => Ok(BitTransform::SwapNibbles),
+            _ => Err(format!("Unknown bit transform: {}", s)),
+        }
+    }
+}
+
+const fn transform_table(transform: BitTransform) -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut b = 0;
+    while b < 256 {
+        table[b] = match transform {
+            BitTransform::None => b as u8,
+            BitTransform::ReverseBits => (b as u8).reverse_bits(),
+            BitTransform::SwapNibbles => (b as u8).rotate_left(4),
+        };
+        b += 1;
+    }
+    table
+}
+
+const REVERSE_BITS_TABLE: [u8; 256] = transform_table(BitTransform::ReverseBits);
+const SWAP_NIBBLES_TABLE: [u8; 256] = transform_table(BitTransform::SwapNibbles);
+
+impl BitTransform {
+    /// Transforms every byte of `data` with a lookup table, keeping the offsets of the data
+    ///
+    /// ```
+    /// use prime_finder::BitTransform;
+    ///
+    /// assert_eq!(BitTransform::ReverseBits.apply(&[0x01, 0xf0]), vec![0x80, 0x0f]);
+    /// assert_eq!(BitTransform::SwapNibbles.apply(&[0x12, 0xf0]), vec![0x21, 0x0f]);
+    /// ```
+    pub fn apply(&self, data: &[u8]) -> Vec<u8> {
+        let table = match self {
+            BitTransform::None => return data.to_vec(),
+            BitTransform::ReverseBits => &REVERSE_BITS_TABLE,
+            BitTransform::SwapNibbles => &SWAP_NIBBLES_TABLE,
+        };
+        data.par_iter().map(|&b| table[b as usize]).collect()
+    }
+}
+
+/// Keeps a pseudorandom `fraction` of the window offsets, the same ones for the same `seed`
+///
+/// Both primes of a key have to be kept for it to be found, which happens with a probability
+/// of `fraction` squared, so a sampled scan can miss keys that are present.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub fraction: f64,
+    pub seed: u64,
+}
+
+impl Sample {
+    /// Whether the window at `offset` is scanned
+    pub fn keeps(&self, offset: usize) -> bool {
+        // splitmix64 of the offset, so the choice doesn't depend on the order windows are visited
+        let mut x = self.seed.wrapping_add((offset as u64).wrapping_mul(0x9e3779b97f4a7c15));
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+        x ^= x >> 31;
+        ((x >> 11) as f64 / (1u64 << 53) as f64) < self.fraction
+    }
+}
+
+/// Rejects windows containing a run of `length` null bytes or bits
+///
+/// In byte mode, `byte` is the value counted as null, e.g. 0xcc for uninitialized MSVC stack
+#[derive(Debug, Clone, Copy)]
+pub struct NullFilter {
+    pub length: usize,
+    pub unit: FilterUnit,
+    pub byte: u8,
+}
+
+impl NullFilter {
+    pub fn rejects(&self, window: &[u8]) -> bool {
+        match self.unit {
+            FilterUnit::Bytes => {
+                // A single pass over the window, instead of checking every run sized sub-window
+                // ./target/release/prime-finder -f 32 -s 256 -p 500KB-of-31-nulls-then-ff  0.81s without, 0.14s with
+                let mut run = 0;
+                for &b in window {
+                    if b == self.byte {
+                        run += 1;
+                        if run >= self.length {
+                            return true;
+                        }
+                    } else {
+                        run = 0;
+                    }
+                }
+                false
+            }
+            FilterUnit::Bits => {
+                // Runs are counted across byte boundaries, most significant bit first
+                let mut run = 0;
+                for &b in window {
+                    if b == 0 {
+                        run += 8;
+                    } else {
+                        for bit in (0..8).rev() {
+                            if b & (1 << bit) == 0 {
+                                run += 1;
+                            } else {
+                                run = 0;
+                            }
+                            if run >= self.length {
+                                return true;
+                            }
+                        }
+                    }
+                    if run >= self.length {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+/// The windows of one size rejected by a byte mode [`NullFilter`], found in a single pass
+///
+/// Runs of the null byte are located with memchr, which compares many bytes at a time, so
+/// the windows don't have to be scanned one by one. Bit mode has no fast path.
+pub struct RejectedWindows {
+    // One bit per window offset
+    bitmap: Vec<u64>,
+}
+
+impl RejectedWindows {
+    pub fn new(data: &[u8], window_size: usize, null_filter: NullFilter) -> Option<RejectedWindows> {
+        if matches!(null_filter.unit, FilterUnit::Bits) {
+            return None;
+        }
+        let windows = (data.len() + 1).saturating_sub(window_size);
+        let mut bitmap = vec![0; windows.div_ceil(64)];
+        if windows == 0 {
+            return Some(RejectedWindows { bitmap });
+        }
+        // A length of 0 rejects windows with a single null byte, like a length of 1
+        let length = null_filter.length.max(1);
+        let mut position = 0;
+        while let Some(found) = memchr(null_filter.byte, &data[position..]) {
+            let start = position + found;
+            let end = data[start..]
+                .iter()
+                .position(|&b| b != null_filter.byte)
+                .map_or(data.len(), |run| start + run);
+            position = end;
+            if end - start < length || length > window_size {
+                continue;
+            }
+            // Every window holding `length` bytes of the run, from the one ending with the
+            // first of them to the one starting with the last
+            let first = (start + length).saturating_sub(window_size);
+            let last = (end - length).min(windows - 1);
+            for offset in first..=last {
+                bitmap[offset / 64] |= 1 << (offset % 64);
+            }
+        }
+        Some(RejectedWindows { bitmap })
+    }
+
+    /// Returns whether the window at `offset` is rejected
+    pub fn contains(&self, offset: usize) -> bool {
+        self.bitmap
+            .get(offset / 64)
+            .is_some_and(|&bits| bits & (1 << (offset % 64)) != 0)
+    }
+}
+
+// Shannon entropy in bits per byte
+fn entropy(window: &[u8]) -> f64 {
+    let mut histogram = [0usize; 256];
+    for &b in window {
+        histogram[b as usize] += 1;
+    }
+    let length = window.len() as f64;
+    histogram
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+fn small_primes(limit: u32) -> Vec<u32> {
+    let mut is_prime = vec![true; limit as usize];
+    let mut primes = Vec::new();
+    for i in 2..limit {
+        if is_prime[i as usize] {
+            primes.push(i);
+            for multiple in (i * i..limit).step_by(i as usize) {
+                is_prime[multiple as usize] = false;
+            }
+        }
+    }
+    primes
+}
+
+fn has_small_factor(number: &Integer, small_primes: &[u32]) -> bool {
+    small_primes
+        .iter()
+        .any(|&p| number.is_divisible_u(p) && *number != p)
+}
+
+/// The filters and tests deciding which windows [`find_candidate_primes`] reports
+#[derive(Debug, Clone, Copy)]
+pub struct CandidateOptions {
+    /// Only windows starting at a multiple of this are tested, so unaligned primes are missed
+    /// unless it is 1
+    pub alignment: usize,
+    /// Windows are also read with the bytes within every limb of this size reversed, as stored
+    /// by big integer libraries whose limb order differs from the byte order within a limb
+    pub limb_size: Option<usize>,
+    /// The byte orders windows are read in
+    pub byte_order: ByteOrder,
+    pub null_filter: NullFilter,
+    /// Windows with less Shannon entropy, in bits per byte, are skipped
+    pub min_entropy: Option<f64>,
+    /// Only the windows rejected by `null_filter` or `min_entropy` are tested instead
+    pub invert_filter: bool,
+    /// Only the offsets kept by the sample are tested
+    pub sample: Option<Sample>,
+    /// The Miller-Rabin rounds of the probable prime test
+    pub mr_rounds: u32,
+    /// Only numbers with the most significant bit of the window set are tested
+    pub msb_set: bool,
+}
+
+impl CandidateOptions {
+    /// Every window is tested in both byte orders, as no run of null bytes is long enough to
+    /// reject it
+    pub const DEFAULT: CandidateOptions = CandidateOptions {
+        alignment: 1,
+        limb_size: None,
+        byte_order: ByteOrder::Both,
+        null_filter: NullFilter {
+            length: usize::MAX,
+            unit: FilterUnit::Bytes,
+            byte: 0,
+        },
+        min_entropy: None,
+        invert_filter: false,
+        sample: None,
+        mr_rounds: 20,
+        msb_set: false,
+    };
+}
+
+impl Default for CandidateOptions {
+    fn default() -> Self {
+        CandidateOptions::DEFAULT
+    }
+}
+
+/// Finds all windows of `prime_size` bytes in `data` that are probable primes
+///
+/// ```
+/// use prime_finder::{
+///     bigint::Integer, find_candidate_primes, CandidateOptions, FilterUnit, NullFilter,
+/// };
+///
+/// // 0x0101 == 257 is prime in both byte orders
+/// let data = [0x00, 0x01, 0x01, 0x00];
+/// let options = CandidateOptions {
+///     null_filter: NullFilter {
+///         length: 2,
+///         unit: FilterUnit::Bytes,
+///         byte: 0,
+///     },
+///     ..CandidateOptions::default()
+/// };
+/// let primes = find_candidate_primes(&data, 2, &options);
+/// assert!(primes.contains(&Integer::from(257)));
+/// ```
+pub fn find_candidate_primes(
+    data: &[u8],
+    prime_size: usize,
+    options: &CandidateOptions,
+) -> HashSet<Integer> {
+    find_candidate_prime_offsets(data, prime_size, options).into_keys().collect()
+}
+
+/// Like [`find_candidate_primes`], but also returns every offset each prime was found at
+pub fn find_candidate_prime_offsets(
+    data: &[u8],
+    prime_size: usize,
+    options: &CandidateOptions,
+) -> CandidateOffsets {
+    candidate_prime_offsets(data, prime_size, options, false)
+}
+
+/// Like [`find_candidate_prime_offsets`], but first records the offsets of all windows passing
+/// the filters and only then tests them for primes
+///
+/// The filter pass is a tight loop over the data, and the expensive tests are spread evenly over
+/// the threads no matter where the surviving windows cluster. The candidates are the same.
+pub fn find_candidate_prime_offsets_two_pass(
+    data: &[u8],
+    prime_size: usize,
+    options: &CandidateOptions,
+) -> CandidateOffsets {
+    candidate_prime_offsets(data, prime_size, options, true)
+}
+
+fn candidate_prime_offsets(
+    data: &[u8],
+    prime_size: usize,
+    options: &CandidateOptions,
+    two_pass: bool,
+) -> CandidateOffsets {
+    // par_windows panics on empty windows
+    if prime_size == 0 {
+        return HashMap::new();
+    }
+    let CandidateOptions {
+        alignment,
+        limb_size,
+        byte_order,
+        null_filter,
+        min_entropy,
+        invert_filter,
+        sample,
+        mr_rounds,
+        msb_set,
+    } = *options;
+    let bar_size = data.len().saturating_sub(prime_size).try_into().unwrap();
+
+    let pb = progress_bar(bar_size);
+
+    let small_primes = small_primes(TRIAL_DIVISION_LIMIT);
+
+    // Number of windows or numbers that made it through each stage
+    let windows = AtomicUsize::new(0);
+    let filtered = AtomicUsize::new(0);
+    let odd_numbers = AtomicUsize::new(0);
+    let full_size = AtomicUsize::new(0);
+    let no_small_factor = AtomicUsize::new(0);
+    let count = |counter: &AtomicUsize| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    };
+
+    let rejected = RejectedWindows::new(data, prime_size, null_filter);
+    let keep_window = |offset: usize, window: &[u8]| {
+        if !offset.is_multiple_of(alignment) || interrupted() {
+            return false;
+        }
+        if !sample.is_none_or(|sample| sample.keeps(offset)) {
+            return false;
+        }
+        count(&windows);
+        // Discard candidates containing too long streaks of 0 bytes or bits
+        let null_rejected = match &rejected {
+            Some(rejected) => rejected.contains(offset),
+            None => null_filter.rejects(window),
+        };
+        let kept = !null_rejected
+            // Low entropy regions like text or padding rarely hold key material
+            && min_entropy.is_none_or(|min_entropy| entropy(window) >= min_entropy);
+        if kept != invert_filter {
+            count(&filtered);
+        }
+        kept != invert_filter
+    };
+
+    // Every rayon job reuses one Integer and limb buffer, so rejected windows allocate
+    // nothing. Only the probable primes are cloned out of the scratch Integer.
+    // ./target/release/prime-finder -f 2 -s 64 -p 500KB-of-urandom  5.63s without, 5.67s with,
+    // trial division and Miller-Rabin dominate either way
+    let scratch = || (Integer::new(), Vec::with_capacity(prime_size));
+    let test_window =
+        |(number, swapped): &mut (Integer, Vec<u8>), offset: usize, window: &[u8]| {
+            // Primes above 2 are odd, so even least significant bytes are skipped before
+            // building the Integer. Only a single byte prime can be 2.
+            // ./target/release/prime-finder -f 2 -s 64 -p 500KB-of-urandom  5.46s without, 5.07s with
+            let odd = |byte: u8| byte & 1 == 1 || prime_size == 1;
+            let mut primes = Vec::new();
+            let mut test = |digits: &[u8], order: Order, layout: &str| {
+                if !byte_order.includes(order) {
+                    return;
+                }
+                let least_significant = match order {
+                    Order::Msf => digits[prime_size - 1],
+                    _ => digits[0],
+                };
+                if !odd(least_significant) {
+                    return;
+                }
+                number.assign_digits(digits, order);
+                count(&odd_numbers);
+                // Real primes of this size have the most significant bit set, in whichever
+                // byte order they were read
+                if msb_set && number.significant_bits() as usize != prime_size * 8 {
+                    return;
+                }
+                count(&full_size);
+                if has_small_factor(number, &small_primes) {
+                    return;
+                }
+                count(&no_small_factor);
+                if number.is_probably_prime(mr_rounds) != IsPrime::No {
+                    debug!("Probable prime at {:#x} read {}: {:#x}", offset, layout, number);
+                    primes.push((number.clone(), offset));
+                }
+            };
+            test(window, Order::Msf, "most significant byte first");
+            test(window, Order::Lsf, "least significant byte first");
+            if let Some(limb_size) = limb_size {
+                swapped.clear();
+                swapped.extend(window.chunks(limb_size).flat_map(|limb| limb.iter().rev()));
+                test(swapped, Order::Msf, "most significant limb first");
+                test(swapped, Order::Lsf, "least significant limb first");
+            }
+            primes
+        };
+
+    info!("Finding candidate primes");
+    let primes = if two_pass {
+        let offsets: Vec<usize> = data
+            .par_windows(prime_size)
+            .progress_with(pb)
+            .enumerate()
+            .filter(|&(offset, window)| keep_window(offset, window))
+            .map(|(offset, _)| offset)
+            .collect();
+        info!("Testing the {} windows that passed the filters", offsets.len());
+        let pb = progress_bar(offsets.len().try_into().unwrap());
+        offsets
+            .par_iter()
+            .progress_with(pb)
+            .filter(|_| !interrupted())
+            .map_init(scratch, |scratch, &offset| {
+                test_window(scratch, offset, &data[offset..offset + prime_size])
+            })
+            .flatten_iter()
+            .collect::<Vec<_>>()
+    } else {
+        data.par_windows(prime_size)
+            .progress_with(pb)
+            .enumerate()
+            .filter(|&(offset, window)| keep_window(offset, window))
+            .map_init(scratch, |scratch, (offset, window)| test_window(scratch, offset, window))
+            .flatten_iter()
+            .collect::<Vec<_>>()
+    };
+
+    // Windows are tested in parallel, so the offsets are sorted once collected
+    let mut candidates = CandidateOffsets::new();
+    for (prime, offset) in primes {
+        candidates.entry(prime).or_default().push(offset);
+    }
+    candidates.par_iter_mut().for_each(|(_, offsets)| offsets.sort_unstable());
+
+    info!(
+        "Candidate funnel: {} windows, {} passed the filters, {} odd numbers, {} passed the MSB \
+         check, {} without small factors, {} distinct probable primes",
+        windows.into_inner(),
+        filtered.into_inner(),
+        odd_numbers.into_inner(),
+        full_size.into_inner(),
+        no_small_factor.into_inner(),
+        candidates.len()
+    );
+    candidates
+}
+
+/// Keeps only the safe primes in `primes`, whose (P-1)/2 is also prime
+///
+/// A heuristic for keys made by strong or safe prime generators, which removes nearly all the
+/// primes found by chance in random data. It also discards every ordinary prime, such as the
+/// ones generated by OpenSSL, so keys are missed unless their generator is known.
+///
+/// ```
+/// use prime_finder::{bigint::Integer, retain_safe_primes};
+///
+/// // (23-1)/2 == 11 is prime, (13-1)/2 == 6 is not
+/// let mut primes = vec![Integer::from(13), Integer::from(23)].into_iter().collect();
+/// retain_safe_primes(&mut primes, 20);
+/// assert_eq!(primes.into_iter().collect::<Vec<_>>(), vec![Integer::from(23)]);
+/// ```
+pub fn retain_safe_primes(primes: &mut HashSet<Integer>, mr_rounds: u32) {
+    info!("Keeping only safe primes");
+    let composite: HashSet<Integer> = primes
+        .par_iter()
+        .filter(|&p| {
+            let half = Integer::from(p - 1) >> 1;
+            half.is_probably_prime(mr_rounds) == IsPrime::No
+        })
+        .cloned()
+        .collect();
+    primes.retain(|p| !composite.contains(p));
+}
+
+/// Rounds of Miller-Rabin used by [`prove_prime`] on numbers too large to be proven prime
+pub const PROOF_ROUNDS: u32 = 100;
+
+// Miller-Rabin to the first 13 prime bases is deterministic below this bound, a bit over 2^81
+// (Sorenson and Webster, Strong pseudoprimes to twelve prime bases, 2015)
+const DETERMINISTIC_LIMIT: &str = "3317044064679887385961981";
+const DETERMINISTIC_BASES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+fn deterministic_limit() -> Integer {
+    Integer::from_str_radix(DETERMINISTIC_LIMIT, 10).unwrap()
+}
+
+/// Tests whether `number` is prime, with certainty when it is below 3.3*10^24
+///
+/// Below that bound Miller-Rabin to the first 13 prime bases never errs, and primes are
+/// reported as `IsPrime::Yes`. Larger numbers are tested with [`PROOF_ROUNDS`] rounds of
+/// `is_probably_prime` instead, so their primality remains probabilistic.
+///
+/// ```
+/// use prime_finder::{bigint::{Integer, IsPrime}, prove_prime};
+///
+/// // 3215031751 is a strong pseudoprime to the bases 2, 3, 5 and 7
+/// assert_eq!(prove_prime(&Integer::from(3215031751u32)), IsPrime::No);
+/// assert_eq!(prove_prime(&((Integer::from(1) << 61) - 1)), IsPrime::Yes);
+/// ```
+pub fn prove_prime(number: &Integer) -> IsPrime {
+    if *number < 2 {
+        return IsPrime::No;
+    }
+    if *number >= deterministic_limit() {
+        return number.is_probably_prime(PROOF_ROUNDS);
+    }
+    for &base in DETERMINISTIC_BASES.iter() {
+        if *number == base {
+            return IsPrime::Yes;
+        }
+        if number.is_divisible_u(base) {
+            return IsPrime::No;
+        }
+    }
+
+    // number - 1 = d * 2^s with d odd
+    let minus_one = Integer::from(number - 1);
+    let mut d = minus_one.clone();
+    let mut s = 0;
+    while d.is_even() {
+        d = d >> 1;
+        s += 1;
+    }
+    let is_witness = |base: u32| {
+        let mut x = Integer::from(base).pow_mod(&d, number).unwrap();
+        if x == 1 || x == minus_one {
+            return false;
+        }
+        for _ in 1..s {
+            x = Integer::from(x.square_ref()) % number;
+            if x == minus_one {
+                return false;
+            }
+        }
+        true
+    };
+    if DETERMINISTIC_BASES.iter().any(|&base| is_witness(base)) {
+        IsPrime::No
+    } else {
+        IsPrime::Yes
+    }
+}
+
+/// Removes the composites that passed the Miller-Rabin rounds of the search from `primes`
+///
+/// Every candidate is tested again with [`prove_prime`], which is only deterministic for
+/// candidates of up to about 81 bits.
+pub fn retain_proven_primes(primes: &mut HashSet<Integer>) {
+    let limit = deterministic_limit();
+    let unproven = primes.iter().filter(|&p| *p >= limit).count();
+    info!("Proving that the candidates are prime");
+    if unproven > 0 {
+        info!(
+            "{} candidates are too large to be proven prime, they remain probable primes after {} rounds of Miller-Rabin",
+            unproven, PROOF_ROUNDS
+        );
+    }
+    let composite: HashSet<Integer> = primes
+        .par_iter()
+        .filter(|&p| prove_prime(p) == IsPrime::No)
+        .cloned()
+        .collect();
+    primes.retain(|p| !composite.contains(p));
+}
+
+// The encodings of N = P*Q in `byte_order`
+//
+// This runs for every pair of primes, so N is multiplied and converted to bytes only once and the
+// least significant byte first encoding is made by reversing the bytes.
+fn n_encodings(p: &Integer, q: &Integer, byte_order: ByteOrder) -> Vec<Vec<u8>> {
+    trace!("N candidate for P:{:#x} Q:{:#x}", p, q);
+    let mut msf = Integer::from(p * q).to_digits::<u8>(Order::Msf);
+    match byte_order {
+        ByteOrder::Msf => vec![msf],
+        ByteOrder::Lsf => {
+            msf.reverse();
+            vec![msf]
+        }
+        ByteOrder::Both => {
+            let lsf: Vec<u8> = msf.iter().rev().copied().collect();
+            // A palindromic N has a single encoding
+            if lsf == msf {
+                vec![lsf]
+            } else {
+                vec![lsf, msf]
+            }
+        }
+    }
+}
+
+/// Builds the map of all N = P*Q, P < Q, encoded in `byte_order`
+///
+/// With `allow_square` every prime is also paired with itself, which is never a real RSA key.
+pub fn build_pqn_map(
+    primes: &HashSet<Integer>,
+    allow_square: bool,
+    byte_order: ByteOrder,
+) -> PqnMap<'_> {
+    info!("Construct N candidates");
+    // Sorting makes pairing every prime with all larger primes yield P < Q
+    let mut primes: Vec<_> = primes.iter().collect();
+    primes.par_sort_unstable();
+    let num_primes = primes.len();
+
+    // Every prime is paired with all larger primes, and itself for squares
+    let skip = usize::from(!allow_square);
+    let num_pairs = if allow_square {
+        num_primes * (num_primes + 1) / 2
+    } else {
+        num_primes * num_primes.saturating_sub(1) / 2
+    };
+    let pb = progress_bar(num_pairs.try_into().unwrap());
+
+    primes
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, &p)| {
+            pb.inc((num_primes - i - skip).try_into().unwrap());
+            primes[i + skip..].iter().flat_map(move |&q| {
+                n_encodings(p, q, byte_order)
+                    .into_iter()
+                    .map(move |n| (n, (p, q)))
+            })
+        })
+        .collect()
+}
+
+/// Like [`build_pqn_map`], but only pairs primes found within `max_gap` bytes of each other
+///
+/// The primes of a key are usually allocated close together, so this prunes most of the pairs of
+/// unrelated primes. Primes missing from `offsets` are never paired.
+pub fn build_nearby_pqn_map<'a>(
+    primes: &'a HashSet<Integer>,
+    offsets: &CandidateOffsets,
+    max_gap: usize,
+    allow_square: bool,
+    byte_order: ByteOrder,
+) -> PqnMap<'a> {
+    info!("Construct N candidates of primes at most {} bytes apart", max_gap);
+    let mut located: Vec<(usize, &Integer)> = primes
+        .iter()
+        .flat_map(|p| {
+            offsets
+                .get(p)
+                .into_iter()
+                .flatten()
+                .map(move |&offset| (offset, p))
+        })
+        .collect();
+    located.par_sort_unstable();
+
+    // Every prime is paired with the ones following it within the gap
+    let mut pairs = HashSet::new();
+    for (i, &(offset, p)) in located.iter().enumerate() {
+        if allow_square {
+            pairs.insert((p, p));
+        }
+        for &(_, q) in located[i + 1..]
+            .iter()
+            .take_while(|&&(other, _)| other - offset <= max_gap)
+        {
+            if p != q {
+                pairs.insert(if p < q { (p, q) } else { (q, p) });
+            }
+        }
+    }
+    info!("Kept {} pairs of nearby primes", pairs.len());
+
+    let pairs: Vec<_> = pairs.into_iter().collect();
+    let pb = progress_bar(pairs.len().try_into().unwrap());
+    pairs
+        .into_par_iter()
+        .progress_with(pb)
+        .flat_map_iter(|(p, q)| {
+            n_encodings(p, q, byte_order)
+                .into_iter()
+                .map(move |n| (n, (p, q)))
+        })
+        .collect()
+}
+
+/// Keeps only the N in `pqn_tuples` whose most significant bytes are `prefix`
+///
+/// Keys stored least significant byte first end with the reversed prefix instead.
+pub fn retain_n_prefix(pqn_tuples: &mut PqnMap, prefix: &[u8]) {
+    let reversed: Vec<u8> = prefix.iter().rev().copied().collect();
+    pqn_tuples.retain(|n, _| n.starts_with(prefix) || n.ends_with(&reversed));
+}
+
+/// Factors `n` with the smallest prime in `primes` that divides it, returning P and N/P
+///
+/// Divisibility is tested without allocating, so only the found factor pair is computed.
+///
+/// ```
+/// use prime_finder::{bigint::Integer, try_factor};
+/// use std::collections::HashSet;
+///
+/// let primes: HashSet<_> = [7, 53, 61].iter().map(|&p| Integer::from(p)).collect();
+/// let factors = try_factor(&Integer::from(3233), &primes);
+/// assert_eq!(factors, Some((Integer::from(53), Integer::from(61))));
+/// assert_eq!(try_factor(&Integer::from(143), &primes), None);
+/// ```
+pub fn try_factor(n: &Integer, primes: &HashSet<Integer>) -> Option<(Integer, Integer)> {
+    let p = primes
+        .iter()
+        .filter(|&p| p != n && *p > 1 && n.is_divisible(p))
+        .min()?;
+    Some((p.clone(), Integer::from(n / p)))
+}
+
+/// Pairs all candidates P < Q whose product N is exactly `modulus_bits` long
+///
+/// For when both primes are in the data but N is not, so no N can be searched for.
+pub fn pair_candidates(primes: &HashSet<Integer>, modulus_bits: u32) -> Vec<(&Integer, &Integer)> {
+    info!("Pair candidates");
+    let mut primes: Vec<_> = primes.iter().collect();
+    primes.par_sort_unstable();
+    let num_primes = primes.len();
+
+    let num_pairs = num_primes * num_primes.saturating_sub(1) / 2;
+    let pb = progress_bar(num_pairs.try_into().unwrap());
+
+    primes
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, &p)| {
+            pb.inc((num_primes - i - 1).try_into().unwrap());
+            primes[i + 1..]
+                .iter()
+                .filter(move |&&q| Integer::from(p * q).significant_bits() == modulus_bits)
+                .map(move |&q| (p, q))
+        })
+        .collect()
+}
+
+/// Builds the map of N = P*`known_prime` for every candidate P, encoded in `byte_order`
+///
+/// Only a linear number of N are constructed, instead of pairing every candidate with every other.
+/// A candidate equal to `known_prime` is skipped unless `allow_square` is set.
+pub fn build_known_pqn_map<'a>(
+    primes: &'a HashSet<Integer>,
+    known_prime: &'a Integer,
+    allow_square: bool,
+    byte_order: ByteOrder,
+) -> PqnMap<'a> {
+    info!("Construct N candidates with the known prime");
+    primes
+        .par_iter()
+        .filter(|&p| allow_square || p != known_prime)
+        .flat_map_iter(|p| {
+            let (p, q) = if p <= known_prime {
+                (p, known_prime)
+            } else {
+                (known_prime, p)
+            };
+            trace!("N candidate for P:{:#x} Q:{:#x}", p, q);
+            let n = Integer::from(p * q);
+            [Order::Lsf, Order::Msf]
+                .iter()
+                .filter(|&&order| byte_order.includes(order))
+                .map(move |&order| (n.to_digits::<u8>(order), (p, q)))
+                .collect::<Vec<_>>()
+                .into_iter()
+        })
+        .collect()
+}
+
+/// Searches `file_contents` for any N in `pqn_tuples` using the given algorithm
+///
+/// `aho_corasick` sets how the automaton is built when `algorithm` is aho-corasick.
+pub fn find_composites<'a>(
+    algorithm: Algorithm,
+    aho_corasick: AhoCorasickOptions,
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+) -> Vec<Match<'a>> {
+    match algorithm {
+        Algorithm::SlidingWindow => finder_sliding_window(pqn_tuples, file_contents, prime_size), // Simple
+        Algorithm::AhoCorasick => finder_aho_corasick(pqn_tuples, file_contents, prime_size, aho_corasick), // Memory expensive
+        Algorithm::RabinKarp => finder_rabin_karp(pqn_tuples, file_contents, prime_size), // Slightly faster
+    }
+}
+
+/// Merges matches at most `distance` bytes after the previous one into clusters
+///
+/// Every cluster is returned as its first match, along with the offsets of all its matches.
+///
+/// ```
+/// use prime_finder::merge_adjacent_matches;
+///
+/// let clusters = merge_adjacent_matches(vec![(40, 'c'), (0, 'a'), (8, 'b')], 16);
+/// assert_eq!(clusters, vec![(0, 'a', vec![0, 8]), (40, 'c', vec![40])]);
+/// ```
+pub fn merge_adjacent_matches<T>(
+    mut matches: Vec<(usize, T)>,
+    distance: usize,
+) -> Vec<(usize, T, Vec<usize>)> {
+    matches.sort_by_key(|&(offset, _)| offset);
+    let mut clusters: Vec<(usize, T, Vec<usize>)> = Vec::new();
+    for (offset, value) in matches {
+        match clusters.last_mut() {
+            Some((_, _, offsets)) if offset - offsets[offsets.len() - 1] <= distance => {
+                offsets.push(offset)
+            }
+            _ => clusters.push((offset, value, vec![offset])),
+        }
+    }
+    clusters
+}
+
+/// Like [`find_composites`], but calls `on_match` with every N as soon as it is found
+///
+/// The search stops once `on_match` returns false, e.g. `|m| sender.send(m).is_ok()` stops
+/// when the receiving end of a channel is dropped. The sliding window calls `on_match` from
+/// several threads, so matches do not arrive in order of offset.
+pub fn stream_composites<'a>(
+    algorithm: Algorithm,
+    aho_corasick: AhoCorasickOptions,
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+    on_match: impl Fn(Match<'a>) -> bool + Sync + Send,
+) {
+    // No N of twice the prime size fits in the file, and the finders never overflow computing it
+    if prime_size == 0 || prime_size > MAX_PRIME_SIZE || 2 * prime_size > file_contents.len() {
+        return;
+    }
+    match algorithm {
+        Algorithm::SlidingWindow => {
+            stream_sliding_window(pqn_tuples, file_contents, prime_size, on_match)
+        }
+        Algorithm::AhoCorasick => {
+            stream_aho_corasick(pqn_tuples, file_contents, prime_size, aho_corasick, on_match)
+        }
+        Algorithm::RabinKarp => stream_rabin_karp(pqn_tuples, file_contents, prime_size, on_match),
+    }
+}
+
+// Runs a streaming finder and collects everything it finds
+fn collect_matches<'a>(stream: impl FnOnce(&(dyn Fn(Match<'a>) -> bool + Sync))) -> Vec<Match<'a>> {
+    let matches = Mutex::new(Vec::new());
+    stream(&|m| {
+        matches.lock().unwrap().push(m);
+        true
+    });
+    matches.into_inner().unwrap()
+}
+
+/// The settings of a [`scan`]
+#[derive(Debug, Clone, Copy)]
+pub struct ScanOptions {
+    pub prime_size: usize,
+    pub null_filter_length: usize,
+    pub algorithm: Algorithm,
+    pub aho_corasick: AhoCorasickOptions,
+    pub mr_rounds: u32,
+}
+
+/// Primes P and Q whose product N was found in the scanned data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedKey {
+    pub p: Integer,
+    pub q: Integer,
+    pub n: Integer,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanResult {
+    /// The number of candidate primes found
+    pub candidates: usize,
+    /// Every pair of candidates whose N was found, in order of offset
+    pub validated: Vec<ValidatedKey>,
+}
+
+/// Finds candidate primes in `data` and every N = P*Q of them stored in `data`
+///
+/// Fails if the prime size is not a possible RSA prime size, or larger than `data`.
+///
+/// ```
+/// use prime_finder::{bigint::Integer, scan, AhoCorasickOptions, Algorithm, ScanOptions};
+///
+/// // P = 0xfff1 and Q = 0xffef, followed by N = 0xffe000ff
+/// let data = [0xff, 0xf1, 0xff, 0xef, 0xff, 0xe0, 0x00, 0xff];
+/// let options = ScanOptions {
+///     prime_size: 2,
+///     null_filter_length: 2,
+///     algorithm: Algorithm::SlidingWindow,
+///     aho_corasick: AhoCorasickOptions::DEFAULT,
+///     mr_rounds: 20,
+/// };
+/// let result = scan(&data, options).unwrap();
+/// assert_eq!(result.validated[0].p, Integer::from(0xffef));
+/// assert_eq!(result.validated[0].q, Integer::from(0xfff1));
+/// assert_eq!(result.validated[0].offset, Some(4));
+/// ```
+pub fn scan(data: &[u8], options: ScanOptions) -> Result<ScanResult, FinderError> {
+    if !(2..=MAX_PRIME_SIZE).contains(&options.prime_size) {
+        return Err(FinderError::InvalidPrimeSize(options.prime_size));
+    }
+    if data.len() < options.prime_size {
+        return Err(FinderError::FileTooSmall {
+            length: data.len(),
+            prime_size: options.prime_size,
+        });
+    }
+    let candidate_options = CandidateOptions {
+        null_filter: NullFilter {
+            length: options.null_filter_length,
+            unit: FilterUnit::Bytes,
+            byte: 0,
+        },
+        mr_rounds: options.mr_rounds,
+        ..CandidateOptions::default()
+    };
+    let primes = find_candidate_primes(data, options.prime_size, &candidate_options);
+    let pqn_tuples = build_pqn_map(&primes, false, ByteOrder::Both);
+
+    let mut validated: Vec<_> =
+        find_composites(
+            options.algorithm,
+            options.aho_corasick,
+            &pqn_tuples,
+            data,
+            options.prime_size,
+        )
+            .into_iter()
+            .map(|(offset, &(p, q))| ValidatedKey {
+                p: p.clone(),
+                q: q.clone(),
+                n: Integer::from(p * q),
+                offset: Some(offset),
+            })
+            .collect();
+    validated.sort_by_key(|key| key.offset);
+
+    Ok(ScanResult {
+        candidates: primes.len(),
+        validated,
+    })
+}
+
+/// Like [`find_composites`], but stops as soon as any N is found
+pub fn find_first_composite<'a>(
+    algorithm: Algorithm,
+    aho_corasick: AhoCorasickOptions,
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+) -> Option<Match<'a>> {
+    let first = Mutex::new(None);
+    stream_composites(algorithm, aho_corasick, pqn_tuples, file_contents, prime_size, |m| {
+        first.lock().unwrap().get_or_insert(m);
+        false
+    });
+    first.into_inner().unwrap()
+}
+
+/// Finds every N = P*Q of two candidates in `file_contents` by dividing the windows by them
+///
+/// No map of N encodings is built, so this uses far less memory than [`find_composites`] on
+/// many candidates, at the cost of dividing every window by the candidates. Like the other
+/// finders, only an N filling the whole window is found, and pairs are returned in order of
+/// offset with P < Q.
+pub fn find_composites_by_division<'a>(
+    primes: &'a HashSet<Integer>,
+    file_contents: &[u8],
+    prime_size: usize,
+    byte_order: ByteOrder,
+) -> Vec<(usize, (&'a Integer, &'a Integer))> {
+    let window_size = 2 * prime_size;
+    if prime_size == 0 || prime_size > MAX_PRIME_SIZE || window_size > file_contents.len() {
+        return Vec::new();
+    }
+    // Sorted, so the division can stop at the square root of the window
+    let mut sorted: Vec<_> = primes.iter().collect();
+    sorted.par_sort_unstable();
+
+    let bar_size = file_contents.len().saturating_sub(prime_size).try_into().unwrap();
+
+    let pb = progress_bar(bar_size);
+
+    info!("Search for composites in file by trial division");
+    let mut matches: Vec<_> = file_contents
+        .par_windows(window_size)
+        .enumerate()
+        .progress_with(pb)
+        .filter(|_| !interrupted())
+        .map_init(Integer::new, |n, (offset, window)| {
+            for order in [Order::Msf, Order::Lsf] {
+                if !byte_order.includes(order) {
+                    continue;
+                }
+                let (most_significant, least_significant) = match order {
+                    Order::Msf => (window[0], window[window_size - 1]),
+                    _ => (window[window_size - 1], window[0]),
+                };
+                // N has no leading zero byte in its encoding, and is odd
+                if most_significant == 0 || least_significant & 1 == 0 {
+                    continue;
+                }
+                n.assign_digits(window, order);
+                let max_bits = n.significant_bits().div_ceil(2);
+                for &p in sorted.iter().take_while(|p| p.significant_bits() <= max_bits) {
+                    if !n.is_divisible(p) {
+                        continue;
+                    }
+                    let q = Integer::from(&*n / p);
+                    if let Some(q) = primes.get(&q).filter(|&q| p < q) {
+                        return Some((offset, (p, q)));
+                    }
+                }
+            }
+            None
+        })
+        .flatten()
+        .collect();
+    matches.sort_unstable_by_key(|&(offset, _)| offset);
+    matches
+}
+
+/// Finds composites in `file_contents` one bit flip away from a candidate prime
+///
+/// This is meant for primes corrupted in memory, e.g. by fault injection. A window at Hamming
+/// distance 1 from the encoding of a prime matches it exactly in one of its halves, so only
+/// the primes sharing a half with the window are compared. Returns the offset of every window,
+/// the prime it differs from and the byte order it was read in, sorted by offset.
+pub fn find_bit_flips<'a>(
+    primes: &'a HashSet<Integer>,
+    file_contents: &[u8],
+    prime_size: usize,
+    byte_order: ByteOrder,
+    mr_rounds: u32,
+) -> Vec<(usize, &'a Integer, Order)> {
+    if prime_size == 0 || prime_size > MAX_PRIME_SIZE || prime_size > file_contents.len() {
+        return Vec::new();
+    }
+
+    // The encodings of the primes, zero padded to the window size
+    let mut encodings = Vec::new();
+    for prime in primes {
+        for order in [Order::Msf, Order::Lsf] {
+            let digits = prime.to_digits::<u8>(order);
+            if !byte_order.includes(order) || digits.len() > prime_size {
+                continue;
+            }
+            let padding = vec![0; prime_size - digits.len()];
+            let digits = match order {
+                Order::Msf => [padding, digits].concat(),
+                _ => [digits, padding].concat(),
+            };
+            encodings.push((digits, prime, order));
+        }
+    }
+    let half = prime_size / 2;
+    let mut first_halves: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let mut second_halves: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (i, (digits, _, _)) in encodings.iter().enumerate() {
+        first_halves.entry(&digits[..half]).or_default().push(i);
+        second_halves.entry(&digits[half..]).or_default().push(i);
+    }
+
+    let bar_size = file_contents.len().saturating_sub(prime_size).try_into().unwrap();
+
+    let pb = progress_bar(bar_size);
+
+    info!("Search for composites one bit flip away from a candidate prime");
+    let mut flips: Vec<_> = file_contents
+        .par_windows(prime_size)
+        .enumerate()
+        .progress_with(pb)
+        .filter(|_| !interrupted())
+        .flat_map_iter(|(offset, window)| {
+            let nearby = first_halves
+                .get(&window[..half])
+                .into_iter()
+                .chain(second_halves.get(&window[half..]))
+                .flatten();
+            // An exact match has distance 0 and is in both halves, any other only in one
+            nearby
+                .map(|&i| &encodings[i])
+                .filter(|(digits, _, _)| {
+                    let distance: u32 = window
+                        .iter()
+                        .zip(digits)
+                        .map(|(a, b)| (a ^ b).count_ones())
+                        .sum();
+                    distance == 1
+                })
+                .map(|&(_, prime, order)| (offset, prime, order))
+                .collect::<Vec<_>>()
+        })
+        .filter(|&(offset, _, order)| {
+            let window = &file_contents[offset..offset + prime_size];
+            Integer::from_digits(window, order).is_probably_prime(mr_rounds) == IsPrime::No
+        })
+        .collect();
+    flips.sort_unstable_by_key(|&(offset, _, _)| offset);
+    flips
+}
+
+// ./target/release/prime-finder -f 4 -s 128 core.ssh-agent.15  82.91s user 22.79s system 130% cpu 1:20.71 total
+pub fn finder_sliding_window<'a>(
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+) -> Vec<Match<'a>> {
+    let mut matches = collect_matches(|on_match| {
+        stream_sliding_window(pqn_tuples, file_contents, prime_size, on_match)
+    });
+    // The windows are searched in parallel, so restore the order of the file
+    matches.sort_unstable_by_key(|&(offset, _)| offset);
+    matches
+}
+
+fn stream_sliding_window<'a>(
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+    on_match: impl Fn(Match<'a>) -> bool + Sync + Send,
+) {
+    let bar_size = file_contents.len().saturating_sub(prime_size).try_into().unwrap();
+
+    let pb = progress_bar(bar_size);
+
+    info!("Search for composites in file");
+    file_contents
+        .par_windows(prime_size * 2)
+        .enumerate()
+        //.progress_count(bar_size)
+        .progress_with(pb)
+        .filter(|_| !interrupted())
+        .filter_map(|(offset, window)| pqn_tuples.get(window).map(|pq| (offset, pq)))
+        .all(on_match);
+}
+
+pub fn finder_aho_corasick<'a>(
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+    options: AhoCorasickOptions,
+) -> Vec<Match<'a>> {
+    collect_matches(|on_match| {
+        stream_aho_corasick(pqn_tuples, file_contents, prime_size, options, on_match)
+    })
+}
+
+fn stream_aho_corasick<'a>(
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+    options: AhoCorasickOptions,
+    on_match: impl Fn(Match<'a>) -> bool,
+) {
+    info!("Building aho-corasick automaton with {:?}", options);
+    // Deprecated as aho-corasick 0.8 always enables them, but they still apply in 0.7
+    #[allow(deprecated)]
+    let ac = AhoCorasickBuilder::new()
+        .dfa(options.dfa)
+        .dense_depth(if options.dense { AHO_CORASICK_DENSE_DEPTH } else { 0 })
+        .byte_classes(options.byte_classes)
+        .premultiply(options.premultiply)
+        .build(pqn_tuples.keys());
+
+    let bar_size = file_contents.len().saturating_sub(prime_size).try_into().unwrap();
+
+    let pb = progress_bar(bar_size);
+
+    info!("Search for composites in file");
+    // Only checked between matches, the automaton itself can't be stopped
+    ac.find_iter(file_contents)
+        .take_while(|_| !interrupted())
+        .progress_with(pb)
+        .flat_map(|m| {
+            pqn_tuples
+                .get(&file_contents[m.start()..m.end()])
+                .map(|pq| (m.start(), pq))
+        })
+        .all(on_match);
+}
+
+fn rabin_karp_hash(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |hash, &b| hash.wrapping_mul(RABIN_KARP_BASE).wrapping_add(b.into()))
+}
+
+pub fn finder_rabin_karp<'a>(
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+) -> Vec<Match<'a>> {
+    rabin_karp_matches(pqn_tuples, file_contents, prime_size).collect()
+}
+
+fn stream_rabin_karp<'a>(
+    pqn_tuples: &'a PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+    on_match: impl Fn(Match<'a>) -> bool,
+) {
+    rabin_karp_matches(pqn_tuples, file_contents, prime_size).all(on_match);
+}
+
+// Lazily rolls the hash over the file, so the search can stop at the first match
+fn rabin_karp_matches<'a: 'b, 'b>(
+    pqn_tuples: &'a PqnMap<'a>,
+    file_contents: &'b [u8],
+    prime_size: usize,
+) -> impl Iterator<Item = Match<'a>> + 'b {
+    let window_size = 2 * prime_size;
+
+    let pb = progress_bar(pqn_tuples.len().try_into().unwrap());
+
+    // Like the sliding window, only N filling the whole window can be found
+    let rabin_hashes: HashSet<u64> = pqn_tuples
+        .keys()
+        .progress_with(pb)
+        .filter(|k| k.len() == window_size)
+        .map(|k| rabin_karp_hash(k))
+        .collect();
+
+    // Weight of the byte leaving the window, RABIN_KARP_BASE^window_size
+    let outgoing_factor =
+        (0..window_size).fold(1u64, |factor, _| factor.wrapping_mul(RABIN_KARP_BASE));
+
+    info!("Search for composites in file");
+    let num_windows = (file_contents.len() + 1).saturating_sub(window_size);
+    let pb = progress_bar(num_windows.try_into().unwrap());
+    let initial_hash = rabin_karp_hash(&file_contents[..window_size.min(file_contents.len())]);
+    (0..num_windows)
+        .scan(initial_hash, move |hash, offset| {
+            if offset > 0 {
+                let outgoing = u64::from(file_contents[offset - 1]);
+                let incoming = u64::from(file_contents[offset + window_size - 1]);
+                *hash = hash
+                    .wrapping_mul(RABIN_KARP_BASE)
+                    .wrapping_add(incoming)
+                    .wrapping_sub(outgoing.wrapping_mul(outgoing_factor));
+            }
+            Some((offset, *hash))
+        })
+        .take_while(|_| !interrupted())
+        .progress_with(pb)
+        .filter(move |(_, hash)| rabin_hashes.contains(hash))
+        // Different windows can share a hash, so a hit is only reported when the map holds
+        // the window bytes themselves
+        .filter_map(move |(offset, _)| {
+            pqn_tuples
+                .get(&file_contents[offset..offset + window_size])
+                .map(|pq| (offset, pq))
+        })
+}
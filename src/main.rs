@@ -1,226 +1,3408 @@
-use aho_corasick::AhoCorasick;
-use cdc::{Polynom64, Rabin64, RollingHash64, SeparatorIter};
-use clap::{Command, Arg};
-use indicatif::ProgressBar;
-use indicatif::{ParallelProgressIterator, ProgressIterator};
-use itertools::Itertools;
+use clap::{Arg, ArgMatches, Command};
+use flate2::read::MultiGzDecoder;
+use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
 use log::{info, warn};
-use rayon::{iter::IntoParallelIterator, iter::ParallelIterator, slice::ParallelSlice};
-use rug::{
-    integer::{IsPrime, Order},
-    Integer,
+use memmap2::Mmap;
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use prime_finder::{
+    base64,
+    batch_gcd::shared_factors,
+    bigint::{Integer, Order},
+    der::{find_der_keys, find_der_primes, find_public_keys, tlv_length},
+    error::FinderError,
+    build_known_pqn_map, build_nearby_pqn_map, build_pqn_map, pair_candidates,
+    find_bit_flips, find_candidate_prime_offsets, find_candidate_prime_offsets_two_pass,
+    find_composites, find_composites_by_division,
+    find_first_composite, merge_adjacent_matches,
+    stream_composites,
+    key::RsaKey,
+    montgomery::find_montgomery_factors,
+    numeric::{find_numeric_runs, numeric_primes},
+    pollard_rho, retain_n_prefix, retain_proven_primes, retain_safe_primes,
+    interrupt, interrupted, multi_progress, set_progress_enabled,
+    set_progress_rate, try_factor, with_progress_bar,
+    AhoCorasickOptions, BitTransform, ByteOrder, CandidateOptions, Sample, Algorithm, FilterUnit, NullFilter, PqnMap, MAX_PRIME_SIZE,
 };
+use rayon::{
+    iter::{IntoParallelRefIterator, ParallelIterator},
+    ThreadPoolBuilder,
+};
+use serde::Serialize;
 use simplelog::{ColorChoice, CombinedLogger, Config, LevelFilter, TermLogger, TerminalMode};
-use std::{collections::HashMap, collections::HashSet, convert::TryInto, fs::read};
+use std::{
+    fs::{create_dir_all, read, read_to_string, remove_file, rename, File},
+    io::{sink, stdin, stdout, BufWriter, Read, Seek, SeekFrom, Write},
+    ops::Deref,
+    collections::{hash_map::{DefaultHasher, Entry}, BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use walkdir::WalkDir;
 
 const PRIMES_WARNING_THRESHOLD: usize = 1_000;
 
-// ./target/release/prime-finder -f 4 -s 128 core.ssh-agent.15  82.91s user 22.79s system 130% cpu 1:20.71 total
-fn finder_sliding_window<'a>(
-    pqn_tuples: &'a HashMap<Vec<u8>, (&Integer, &Integer)>,
-    file_contents: &[u8],
-    prime_size: usize,
-) -> Vec<&'a (&'a Integer, &'a Integer)> {
-    let bar_size = (file_contents.len() - prime_size).try_into().unwrap();
-
-    let pb = ProgressBar::new(bar_size);
-    pb.set_draw_rate(4);
-
-    info!("Search for composites in file");
-    file_contents
-        .par_windows(prime_size * 2)
-        //.progress_count(bar_size)
-        .progress_with(pb)
-        .filter_map(|window| pqn_tuples.get(window))
+// Prime sizes in bytes that are more likely meant as RSA sizes in bits
+const COMMON_RSA_BITS: [usize; 4] = [1024, 2048, 3072, 4096];
+
+// Bytes read from a device at a time, unless --chunk-size is given
+const DEVICE_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+// Exit codes, so scripts can tell empty results from errors
+const EXIT_FOUND: i32 = 0;
+const EXIT_NOT_FOUND: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+// Like shells report a process killed by SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
+
+// How long --watch waits for writes to a file to stop before rescanning it
+const WATCH_DELAY: Duration = Duration::from_secs(1);
+
+// Rough number of bytes besides the N encoding used by every entry of the N map:
+// the Vec header, the two prime references and the hash table control byte
+const PQN_ENTRY_OVERHEAD: usize = 24 + 16 + 1;
+
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Csv,
+    Jsonl,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "csv" => Ok(OutputFormat::Csv),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Compression {
+    Auto,
+    Gzip,
+    Zstd,
+    None,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Compression::Auto),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            "none" => Ok(Compression::None),
+            _ => Err(format!("Unknown compression: {}", s)),
+        }
+    }
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const PEM_BEGIN: &[u8] = b"-----BEGIN ";
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// Hex output is zero padded to `size` bytes and written in the given byte order,
+// so it lines up with the bytes in the file
+fn format_integer(value: &Integer, hex: bool, size: usize, order: Order) -> String {
+    if !hex {
+        return value.to_string();
+    }
+    let digits = value.to_digits::<u8>(Order::Msf);
+    let mut bytes = vec![0u8; size.saturating_sub(digits.len())];
+    bytes.extend_from_slice(&digits);
+    if let Order::Lsf = order {
+        bytes.reverse();
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Formats `data` like hexdump -C, 16 bytes per line numbered from `start`
+fn hexdump(data: &[u8], start: usize) -> String {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, line)| {
+            let hex: Vec<_> = line.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = line
+                .iter()
+                .map(|&b| if b == b' ' || b.is_ascii_graphic() { b as char } else { '.' })
+                .collect();
+            format!("  {:08x}  {:<47}  |{}|\n", start + 16 * i, hex.join(" "), ascii)
+        })
         .collect()
 }
 
-fn finder_aho_corasick<'a>(
-    pqn_tuples: &'a HashMap<Vec<u8>, (&Integer, &Integer)>,
-    file_contents: &[u8],
-    prime_size: usize,
-) -> Vec<&'a (&'a Integer, &'a Integer)> {
-    let composites = pqn_tuples.keys();
-    let ac = AhoCorasick::new(composites);
+// One line of --format jsonl, integers are hex strings to avoid precision loss
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Record {
+    Match {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+        offset: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        decoded_offset: Option<usize>,
+        p: String,
+        q: String,
+        n: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        e: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        d: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dp: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dq: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        qinv: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        offsets: Option<Vec<usize>>,
+    },
+    Factor {
+        n: String,
+        p: String,
+        q: String,
+    },
+    Pair {
+        p: String,
+        q: String,
+        n: String,
+    },
+    BitFlip {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<String>,
+        offset: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        decoded_offset: Option<usize>,
+        prime: String,
+        composite: String,
+    },
+    Certificate {
+        certificate: String,
+        n: String,
+        p: String,
+        q: String,
+    },
+    SharedFactor {
+        a: String,
+        b: String,
+        gcd: String,
+    },
+    Prime {
+        prime: String,
+    },
+    File {
+        file: String,
+    },
+    Region {
+        start: usize,
+        end: usize,
+        name: String,
+    },
+    PrimeSize {
+        prime_size: usize,
+    },
+    BitLength {
+        bits: u32,
+        count: usize,
+    },
+}
 
-    let bar_size = (file_contents.len() - prime_size).try_into().unwrap();
+fn json_integer(value: &Integer) -> String {
+    format!("{:#x}", value)
+}
+
+// Accepts decimal, or hexadecimal with a 0x prefix
+fn parse_integer(s: &str) -> Result<Integer, Box<dyn std::error::Error>> {
+    let s = s.trim();
+    let integer = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => Integer::from_str_radix(hex, 16),
+        None => Integer::from_str_radix(s, 10),
+    };
+    integer.map_err(|err| format!("Invalid integer {}: {}", s, err).into())
+}
 
-    let pb = ProgressBar::new(bar_size);
-    pb.set_draw_rate(4);
+fn byte_length(value: &Integer) -> usize {
+    (value.significant_bits() as usize).div_ceil(8)
+}
 
-    info!("Search for composites in file");
-    ac.find_iter(file_contents)
-        .progress_with(pb)
-        .flat_map(|m| pqn_tuples.get(&file_contents[m.start()..m.end()]))
+// Parses hex digits, with or without a 0x prefix, into bytes
+fn parse_hex_bytes(value: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let digits = value.trim_start_matches("0x");
+    if digits.is_empty() || !digits.is_ascii() || !digits.len().is_multiple_of(2) {
+        return Err(format!("Invalid hex bytes {}: expected an even number of hex digits", value).into());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|err| format!("Invalid hex bytes {}: {}", value, err).into())
+        })
         .collect()
 }
 
-// ./target/release/prime-finder -f 4 -s 128 core.ssh-agent.15  70.59s user 11.57s system 137% cpu 59.732 total
-fn finder_rabin_karp<'a>(
-    pqn_tuples: &'a HashMap<Vec<u8>, (&Integer, &Integer)>,
+// Cache file of the candidates found in this data with every parameter that affects them.
+// DefaultHasher may change between Rust releases, which only turns into cache misses.
+fn cache_path(
+    cache_dir: &Path,
     file_contents: &[u8],
     prime_size: usize,
-) -> Vec<&'a (&'a Integer, &'a Integer)> {
-    let bit_size = 8; /*(2*prime_size*8).try_into().unwrap();*/
-    let mut hasher = Rabin64::new(bit_size);
+    options: &Options,
+) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    file_contents.hash(&mut hasher);
+    let parameters = (
+        prime_size,
+        options.alignment,
+        options.limb_size,
+        options.byte_order,
+        options.null_filter,
+        options.min_entropy,
+        options.invert_filter,
+        options.mr_rounds,
+        options.msb_set,
+        options.der_aware,
+        // A sampled search only finds some of the candidates
+        options.sample,
+    );
+    format!("{:?}", parameters).hash(&mut hasher);
+    cache_dir.join(format!("{:016x}.primes", hasher.finish()))
+}
 
-    let bar_size = (file_contents.len() - prime_size).try_into().unwrap();
+// Writes the candidates as bare hex, like --dump-primes --hex, so read_primes can load them
+fn write_cache(path: &Path, primes: &HashSet<Integer>) -> Result<(), Box<dyn std::error::Error>> {
+    // Written next to the cache file first, so an interrupted run leaves no partial entry
+    let partial = path.with_extension("partial");
+    let mut file = BufWriter::new(File::create(&partial)?);
+    for prime in primes {
+        writeln!(file, "{:x}", prime)?;
+    }
+    file.flush()?;
+    drop(file);
+    rename(&partial, path)?;
+    info!("Cached {} prime candidates in {}", primes.len(), path.display());
+    Ok(())
+}
 
-    let pb = ProgressBar::new(bar_size);
-    pb.set_draw_rate(4);
+// The progress of a chunked scan, so that it can be resumed with the same parameters
+struct Checkpoint {
+    path: PathBuf,
+    parameters: u64,
+    // The prime size being scanned and the offset of the first chunk not fully scanned
+    resume: Option<(usize, usize)>,
+}
 
-    let rabin_pqn_tuples: HashMap<Polynom64, &(&Integer, &Integer)> = pqn_tuples
-        .iter()
-        .progress_with(pb)
-        .map(|(k, v)| {
-            hasher.reset();
-            for b in k.iter() {
-                hasher.slide(b);
+impl Checkpoint {
+    // Loads the checkpoint at `path` if there is one, refusing it if the parameters differ
+    fn open(path: &Path, parameters: u64) -> Result<Checkpoint, Box<dyn std::error::Error>> {
+        let resume = if path.exists() {
+            let contents = read_to_string(path)?;
+            let fields: Vec<_> = contents.split_whitespace().collect();
+            let (saved, prime_size, offset) = match fields[..] {
+                [saved, prime_size, offset] => (saved, prime_size, offset),
+                _ => return Err(format!("Invalid checkpoint {}", path.display()).into()),
+            };
+            if u64::from_str_radix(saved, 16)? != parameters {
+                return Err(format!(
+                    "Checkpoint {} was written with different parameters, remove it to start over",
+                    path.display()
+                )
+                .into());
             }
-            (*hasher.get_hash(), v)
+            let (prime_size, offset) = (prime_size.parse::<usize>()?, offset.parse::<usize>()?);
+            info!(
+                "Resuming the scan for {} byte primes at {:#x} from {}",
+                prime_size,
+                offset,
+                path.display()
+            );
+            Some((prime_size, offset))
+        } else {
+            None
+        };
+        Ok(Checkpoint {
+            path: path.to_path_buf(),
+            parameters,
+            resume,
         })
+    }
+
+    // Where the scan for `prime_size` resumes, sizes are scanned from the smallest up
+    fn resume_offset(&self, prime_size: usize) -> usize {
+        match self.resume {
+            Some((size, offset)) if size == prime_size => offset,
+            Some((size, _)) if size > prime_size => usize::MAX,
+            _ => 0,
+        }
+    }
+
+    // Records that every window before `offset` has been scanned for `prime_size`
+    fn save(&self, prime_size: usize, offset: usize) -> Result<(), Box<dyn std::error::Error>> {
+        // Written next to the checkpoint first, like the cache, so it is never left partial
+        let partial = self.path.with_extension("partial");
+        let mut file = File::create(&partial)?;
+        writeln!(file, "{:016x} {} {}", self.parameters, prime_size, offset)?;
+        drop(file);
+        rename(&partial, &self.path)?;
+        Ok(())
+    }
+}
+
+// Fingerprint of the input and every parameter that affects which chunks hold what results
+fn checkpoint_parameters(file_name: &str, options: &Options) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let region = (file_name, options.start, options.end, options.chunk_size);
+    let parameters = (
+        region,
+        &options.prime_sizes,
+        options.alignment,
+        (options.limb_size, options.byte_order),
+        options.null_filter,
+        options.min_entropy,
+        options.invert_filter,
+        options.bit_transform,
+        options.sample,
+        options.mr_rounds,
+        options.msb_set,
+        options.der_aware,
+    );
+    format!("{:?}", parameters).hash(&mut hasher);
+    hasher.finish()
+}
+
+// Reads primes written by --dump-primes in any output format, skipping its other lines
+fn read_primes(path: &str, hex: bool) -> Result<HashSet<Integer>, Box<dyn std::error::Error>> {
+    let mut primes = HashSet::new();
+    for line in read_to_string(path)?.lines().map(str::trim) {
+        if line.is_empty()
+            || line == "Primes in file"
+            || line == "Primes of validated keys in file"
+            || line == "prime"
+            || line.starts_with("File: ")
+            || line.starts_with("Prime size: ")
+        {
+            continue;
+        }
+        let prime = if line.starts_with('{') {
+            match line
+                .strip_prefix("{\"prime\":\"")
+                .and_then(|line| line.strip_suffix("\"}"))
+            {
+                Some(value) => parse_integer(value)?,
+                None => continue,
+            }
+        } else if hex {
+            // --hex prints the digits without a prefix
+            Integer::from_str_radix(line, 16)
+                .map_err(|err| format!("Invalid integer {}: {}", line, err))?
+        } else {
+            parse_integer(line)?
+        };
+        primes.insert(prime);
+    }
+    Ok(primes)
+}
+
+// The name and modulus of every RSA key in a PEM or DER bundle of certificates
+fn read_certs(path: &str) -> Result<Vec<(String, Integer)>, Box<dyn std::error::Error>> {
+    let data = read(path)?;
+    let documents = if data.windows(PEM_BEGIN.len()).any(|window| window == PEM_BEGIN) {
+        base64::decode_pem(&data)
+    } else {
+        vec![data]
+    };
+    let mut certs = Vec::new();
+    for key in documents.iter().flat_map(|document| find_public_keys(document)) {
+        // Numbered in bundle order, as several certificates can share a subject
+        let name = match key.common_name {
+            Some(common_name) => format!("#{} CN={}", certs.len() + 1, common_name),
+            None => format!("#{}", certs.len() + 1),
+        };
+        certs.push((name, key.n));
+    }
+    if certs.is_empty() {
+        return Err(format!("No RSA public keys found in {}", path).into());
+    }
+    info!("Loaded {} RSA public keys from {}", certs.len(), path);
+    Ok(certs)
+}
+
+// Where each file starts in the data scanned with --concat, and its name
+type ConcatFiles = Vec<(usize, String)>;
+
+struct Options {
+    dump_primes: bool,
+    histogram: bool,
+    emit_key: bool,
+    with_d: bool,
+    dedup: bool,
+    merge_adjacent: bool,
+    dump_validated_primes: bool,
+    two_pass: bool,
+    exponents: Vec<u32>,
+    msb_set: bool,
+    strong_only: bool,
+    prove: bool,
+    hex: bool,
+    context: Option<usize>,
+    prime_sizes: Vec<usize>,
+    alignment: usize,
+    limb_size: Option<usize>,
+    byte_order: ByteOrder,
+    algorithm: Algorithm,
+    aho_corasick: AhoCorasickOptions,
+    format: OutputFormat,
+    null_filter: NullFilter,
+    min_entropy: Option<f64>,
+    invert_filter: bool,
+    bit_transform: BitTransform,
+    sample: Option<Sample>,
+    mr_rounds: u32,
+    start: Option<usize>,
+    end: Option<usize>,
+    max_primes: Option<usize>,
+    truncate: bool,
+    moduli: Vec<Integer>,
+    certs: Vec<(String, Integer)>,
+    known_prime: Option<Integer>,
+    n_prefix: Option<Vec<u8>>,
+    primes_in: Option<HashSet<Integer>>,
+    // Well-known primes, e.g. of DH groups, that are never paired
+    exclude_primes: HashSet<Integer>,
+    cache_dir: Option<PathBuf>,
+    pollard_rho: Option<u64>,
+    montgomery: bool,
+    chunk_size: Option<usize>,
+    estimate: bool,
+    device: bool,
+    stop_after_first: bool,
+    batch_gcd: bool,
+    pair_candidates: bool,
+    invert_primes: bool,
+    trial_division: bool,
+    allow_square: bool,
+    max_offset_gap: Option<usize>,
+    compression: Compression,
+    benchmark: bool,
+    scan_base64: bool,
+    der_aware: bool,
+    ssh: bool,
+    jwk: bool,
+    key_struct: bool,
+    ascii_numeric: bool,
+    concat_files: ConcatFiles,
+    checkpoint: Option<Checkpoint>,
+}
+
+struct Output {
+    results: Box<dyn Write + Send>,
+    keys: Option<Box<dyn Write + Send>>,
+    summary: Summary,
+    // The file being scanned, named in the comment of --ssh keys
+    file_name: String,
+    // The keys of --format pem-bundle, written sorted at the end
+    bundle: Option<Vec<BundleEntry>>,
+    // The pairs already printed by earlier scans of --watch, which are not printed again
+    reported: Option<HashSet<(Integer, Integer)>>,
+    // While scanning overlapping chunks, the primes dumped by the last chunk and their offsets
+    // in the file, as the next chunk finds those in the overlap again
+    dumped: Option<HashSet<(usize, Integer)>>,
+}
+
+// A key of --format pem-bundle, sorted by file, offset and exponent
+struct BundleEntry {
+    file: String,
+    offset: Option<usize>,
+    e: Integer,
+    pem: String,
+}
+
+// Totals over all scanned files, logged at the end of the run
+#[derive(Default)]
+struct Summary {
+    bytes: usize,
+    candidates: usize,
+    validated: usize,
+    candidate_time: Duration,
+    finder_time: Duration,
+}
+
+impl Summary {
+    fn add(&mut self, other: &Summary) {
+        self.bytes += other.bytes;
+        self.candidates += other.candidates;
+        self.validated += other.validated;
+        self.candidate_time += other.candidate_time;
+        self.finder_time += other.finder_time;
+    }
+}
+
+// Collects what is written to it, so the results of a file can be printed all at once
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Buffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    // With --stop-after-first, nothing more is scanned once a key has been found
+    fn done(&self, options: &Options) -> bool {
+        interrupted() || options.stop_after_first && self.summary.validated > 0
+    }
+
+    // Keys go with the results unless --key-out is given
+    fn keys(&mut self) -> &mut dyn Write {
+        match &mut self.keys {
+            Some(keys) => keys.as_mut(),
+            None => self.results.as_mut(),
+        }
+    }
+
+    // Writes a key for --emit-key, as PEM, with --ssh in the OpenSSH format or with --jwk as a JWK.
+    // Keys of --format pem-bundle are kept until the end instead, each after a comment line.
+    fn emit_key(
+        &mut self,
+        key: &RsaKey,
+        options: &Options,
+        (file, offset): (&str, Option<usize>),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let comment = match offset {
+            Some(offset) => format!("{} at {:#x} e={}", file, offset, key.e),
+            None => format!("{} e={}", file, key.e),
+        };
+        if let Some(bundle) = &mut self.bundle {
+            bundle.push(BundleEntry {
+                file: file.to_string(),
+                offset,
+                e: key.e.clone(),
+                pem: format!("# {}\n{}", comment, key.to_pem()),
+            });
+            return Ok(());
+        }
+        let encoded = if options.ssh {
+            key.to_openssh(&comment)
+        } else if options.jwk {
+            format!("{}\n", key.to_jwk())
+        } else {
+            key.to_pem()
+        };
+        self.keys().write_all(encoded.as_bytes())?;
+        Ok(())
+    }
+}
+
+// Where scanned data came from, so offsets can be reported relative to the whole file
+enum Location<'a> {
+    // The start of the scanned region
+    File(usize),
+    // The offsets of every run in the decoded data, and of its base64 text in the file
+    Base64(Vec<(usize, usize)>),
+    // The start of the scanned region in the concatenated files, and where each file starts
+    Concat(usize, &'a [(usize, String)]),
+}
+
+impl Location<'_> {
+    fn csv_header(&self) -> &'static str {
+        match self {
+            Location::File(_) => "offset",
+            Location::Base64(_) => "offset,decoded_offset",
+            Location::Concat(..) => "file,offset",
+        }
+    }
+
+    // The offset in the file, and for base64 the offset in the decoded run
+    fn offsets(&self, offset: usize) -> (usize, Option<usize>) {
+        match self {
+            Location::File(start) => (start + offset, None),
+            Location::Base64(run_offsets) => {
+                let run = run_offsets.partition_point(|&(decoded_start, _)| decoded_start <= offset) - 1;
+                let (decoded_start, text_offset) = run_offsets[run];
+                (text_offset, Some(offset - decoded_start))
+            }
+            Location::Concat(start, files) => {
+                let (file_start, _) = &files[concat_file_index(files, start + offset)];
+                (start + offset - file_start, None)
+            }
+        }
+    }
+
+    // The file holding the offset, when several files are scanned as one
+    fn file(&self, offset: usize) -> Option<&str> {
+        match self {
+            Location::Concat(start, files) => {
+                Some(&files[concat_file_index(files, start + offset)].1)
+            }
+            _ => None,
+        }
+    }
+
+    fn describe(&self, offset: usize, format: OutputFormat) -> String {
+        let offsets = self.describe_offsets(offset, format);
+        match (self.file(offset), format) {
+            (Some(file), OutputFormat::Csv) => format!("{},{}", file, offsets),
+            (Some(file), _) => format!("file={} {}", file, offsets),
+            (None, _) => offsets,
+        }
+    }
+
+    fn describe_offsets(&self, offset: usize, format: OutputFormat) -> String {
+        match (self.offsets(offset), format) {
+            ((offset, None), OutputFormat::Csv) => format!("{}", offset),
+            ((offset, Some(decoded_offset)), OutputFormat::Csv) => {
+                format!("{},{}", offset, decoded_offset)
+            }
+            ((offset, None), _) => format!("offset={:#x}", offset),
+            ((offset, Some(decoded_offset)), _) => {
+                format!("offset={:#x} decoded_offset={:#x}", offset, decoded_offset)
+            }
+        }
+    }
+}
+
+fn concat_file_index(files: &[(usize, String)], offset: usize) -> usize {
+    files.partition_point(|&(file_start, _)| file_start <= offset) - 1
+}
+
+// Where offsets in the scanned region starting at `start` are reported
+fn file_location(start: usize, options: &Options) -> Location<'_> {
+    if options.concat_files.is_empty() {
+        Location::File(start)
+    } else {
+        Location::Concat(start, &options.concat_files)
+    }
+}
+
+enum FileData {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileData::Mapped(mmap) => mmap,
+            FileData::Read(data) => data,
+        }
+    }
+}
+
+fn open_file(file_name: &str) -> std::io::Result<FileData> {
+    // stdin has no known length and can't be mapped, so it is read fully up front
+    if file_name == "-" {
+        let mut data = Vec::new();
+        stdin().lock().read_to_end(&mut data)?;
+        return Ok(FileData::Read(data));
+    }
+
+    let file = File::open(file_name)?;
+    // Safety: the mapping is only read, and is kept alive until the file has been scanned
+    match unsafe { Mmap::map(&file) } {
+        Ok(mmap) => Ok(FileData::Mapped(mmap)),
+        Err(err) => {
+            warn!("Failed to memory map {}, reading it instead: {}", file_name, err);
+            Ok(FileData::Read(read(file_name)?))
+        }
+    }
+}
+
+// Compressed data can't be scanned in place, so it is decompressed into memory
+fn decompress(data: FileData, compression: Compression) -> std::io::Result<FileData> {
+    let compression = match compression {
+        Compression::Auto if data.starts_with(GZIP_MAGIC) => Compression::Gzip,
+        Compression::Auto if data.starts_with(ZSTD_MAGIC) => Compression::Zstd,
+        Compression::Auto => Compression::None,
+        compression => compression,
+    };
+
+    let decompressed = match compression {
+        Compression::Gzip => {
+            let mut decompressed = Vec::new();
+            MultiGzDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+            decompressed
+        }
+        Compression::Zstd => zstd::decode_all(&data[..])?,
+        Compression::Auto | Compression::None => return Ok(data),
+    };
+    info!(
+        "Decompressed {:?} input to {} bytes",
+        compression,
+        decompressed.len()
+    );
+    Ok(FileData::Read(decompressed))
+}
+
+// Runs every finder over the same input and checks that they agree
+fn benchmark_finders(
+    pqn_tuples: &PqnMap,
+    file_contents: &[u8],
+    prime_size: usize,
+    aho_corasick: AhoCorasickOptions,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut results = Vec::new();
+    for algorithm in Algorithm::ALL {
+        info!("Benchmarking {:?} algorithm", algorithm);
+        let start = Instant::now();
+        let mut matches: Vec<_> =
+            find_composites(algorithm, aho_corasick, pqn_tuples, file_contents, prime_size)
+                .into_iter()
+                .map(|(offset, (p, q))| (offset, *p, *q))
+                .collect();
+        let elapsed = start.elapsed();
+        matches.sort_unstable();
+        results.push((algorithm, elapsed, matches));
+    }
+
+    writeln!(output.results, "{:<16}{:>12}{:>10}", "Algorithm", "Elapsed", "Matches")?;
+    for (algorithm, elapsed, matches) in &results {
+        writeln!(
+            output.results,
+            "{:<16}{:>11.3}s{:>10}",
+            format!("{:?}", algorithm),
+            elapsed.as_secs_f64(),
+            matches.len()
+        )?;
+    }
+
+    let (reference, _, expected) = &results[0];
+    for (algorithm, _, matches) in &results[1..] {
+        if matches != expected {
+            return Err(format!(
+                "{:?} found {} matches, which differ from the {} found by {:?}",
+                algorithm,
+                matches.len(),
+                expected.len(),
+                reference
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+// Reads every file into one buffer, along with the offset each of them starts at
+fn read_concat(
+    file_names: &[&str],
+    compression: Compression,
+) -> Result<(Vec<u8>, ConcatFiles), Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    let mut files = Vec::new();
+    for &file_name in file_names {
+        files.push((data.len(), file_name.to_string()));
+        data.extend_from_slice(&decompress(open_file(file_name)?, compression)?);
+    }
+    info!("Concatenated {} files into {} bytes", files.len(), data.len());
+    Ok((data, files))
+}
+
+// Names the file whose results follow, when scanning a directory
+fn write_file_label(path: &str, options: &Options, results: &mut dyn Write) -> std::io::Result<()> {
+    match options.format {
+        OutputFormat::Jsonl => {
+            let record = Record::File {
+                file: path.to_string(),
+            };
+            writeln!(results, "{}", serde_json::to_string(&record)?)
+        }
+        _ => writeln!(results, "File: {}", path),
+    }
+}
+
+// Scans the files of --recursive concurrently for --parallel-files. The files and the searches
+// within them share the rayon pool, so --threads still bounds the number of threads. Results
+// are printed for a whole file at a time, in the order the files are done.
+fn scan_files_parallel(
+    paths: &[String],
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let multi = multi_progress();
+    let files_bar = multi.add(ProgressBar::new(paths.len() as u64));
+    // A bar for every thread, borrowed by the file it is scanning
+    let style = ProgressStyle::default_bar().template("{prefix} {wide_bar} {pos}/{len}");
+    let bars: Vec<_> = (0..rayon::current_num_threads())
+        .map(|_| multi.add(ProgressBar::new(0).with_style(style.clone())))
         .collect();
+    let free_bars = Mutex::new(bars.clone());
+    let drawer = thread::spawn(move || multi.join_and_clear());
 
-    SeparatorIter::custom_new(file_contents.iter().cloned(), bit_size, |candidate_hash| {
-        rabin_pqn_tuples.contains_key(&candidate_hash)
-    })
-    .flat_map(|separator| {
-        pqn_tuples.get(
-            &file_contents[separator.index as usize - 2 * prime_size..(separator.index as usize)],
-        )
-    })
-    .collect()
+    let keys_separate = output.keys.is_some();
+    let bundling = output.bundle.is_some();
+    let output = Mutex::new(output);
+    let result = paths
+        .par_iter()
+        .progress_with(files_bar.clone())
+        .try_for_each(|path| -> std::io::Result<()> {
+            if output.lock().unwrap().done(options) {
+                return Ok(());
+            }
+            let results = Buffer::default();
+            let keys = Buffer::default();
+            let mut file_output = Output {
+                results: Box::new(results.clone()),
+                keys: if keys_separate {
+                    Some(Box::new(keys.clone()))
+                } else {
+                    None
+                },
+                summary: Summary::default(),
+                file_name: path.clone(),
+                bundle: bundling.then(Vec::new),
+                reported: None,
+                dumped: None,
+            };
+            write_file_label(path, options, file_output.results.as_mut())?;
+
+            // More files than threads are in progress when a thread waiting on its search
+            // starts another file, which then goes without a bar
+            let bar = free_bars.lock().unwrap().pop().unwrap_or_else(ProgressBar::hidden);
+            bar.set_prefix(path.clone());
+            if let Err(err) = with_progress_bar(&bar, || scan_file(path, options, &mut file_output)) {
+                warn!("Skipping {}: {}", path, err);
+            }
+            free_bars.lock().unwrap().push(bar);
+
+            let mut output = output.lock().unwrap();
+            output.summary.add(&file_output.summary);
+            if let (Some(bundle), Some(keys)) = (&mut output.bundle, file_output.bundle) {
+                bundle.extend(keys);
+            }
+            output.results.write_all(&results.take())?;
+            output.results.flush()?;
+            if let Some(keys_out) = &mut output.keys {
+                keys_out.write_all(&keys.take())?;
+            }
+            Ok(())
+        });
+
+    for bar in bars.iter().chain([&files_bar]) {
+        bar.finish_and_clear();
+    }
+    drawer.join().expect("The progress bar thread panicked")?;
+    result?;
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    CombinedLogger::init(vec![TermLogger::new(
-        LevelFilter::Info,
-        Config::default(),
-        TerminalMode::Mixed,
-        ColorChoice::Auto,
-    )])?;
+// Rescans the file whenever it has been written to, until Ctrl-C. The writes must stop for
+// WATCH_DELAY first, and a key cut off by a partial write is still new when the next scan finds
+// it whole.
+fn watch_file(
+    file_name: &str,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The directory is watched, so the file is still followed when it is replaced by a new one
+    let path = Path::new(file_name).canonicalize()?;
+    let directory = path.parent().ok_or("--watch needs a file in a directory")?;
+    let (sender, receiver) = mpsc::channel();
+    let mut file_watcher = watcher(sender, WATCH_DELAY)?;
+    file_watcher.watch(directory, RecursiveMode::NonRecursive)?;
+    info!("Watching {} for changes, press Ctrl-C to stop", file_name);
 
-    //TODO: add start/end command line arguments
-    let matches = Command::new("prime-finder")
-        .version("0.2")
-        .about("Finds RSA primes in files")
-        .author("Calle Svensson <calle.svensson@zeta-two.com>")
-        .arg(
-            Arg::new("prime_size")
-                .short('s')
-                .long("prime-size")
-                .value_name("SIZE")
-                .help("Sets the size in bytes of the prime numbers to search for")
-                .required(true)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::new("dump_primes")
-                .short('p')
-                .long("dump-primes")
-                .help("Prints all primes without verifying P*Q"),
-        )
-        .arg(
-            Arg::new("null_filter_length")
-                .short('f')
-                .long("null-filter-length")
-                .value_name("LENGTH")
-                .help("Filters out any primes with a sequence of null bytes this long")
-                .required(true)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::new("FILE")
-                .help("Sets the input file to use")
-                .required(true)
-                .index(1),
+    while !interrupted() {
+        let changed = match receiver.recv_timeout(Duration::from_millis(200)) {
+            Ok(DebouncedEvent::Create(changed))
+            | Ok(DebouncedEvent::Write(changed))
+            | Ok(DebouncedEvent::Rename(_, changed)) => changed,
+            Ok(DebouncedEvent::Error(err, _)) => {
+                warn!("Failed to watch {}: {}", file_name, err);
+                continue;
+            }
+            Ok(_) | Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if changed != path {
+            continue;
+        }
+        info!("Rescanning {}", file_name);
+        // The file can be gone again by the time it is read
+        if let Err(err) = scan_file(file_name, options, output) {
+            warn!("Failed to rescan {}: {}", file_name, err);
+        }
+        output.results.flush()?;
+        output.keys().flush()?;
+    }
+    Ok(())
+}
+
+fn scan_file(
+    file_name: &str,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    output.file_name = file_name.to_string();
+    if options.device || is_device(file_name) {
+        return scan_device(file_name, options, output);
+    }
+    let file_data = decompress(open_file(file_name)?, options.compression)?;
+
+    let start = options.start.unwrap_or(0);
+    let end = options.end.unwrap_or(file_data.len());
+    if start > end || end > file_data.len() {
+        return Err(format!(
+            "Invalid scan region {}..{} for file of {} bytes",
+            start,
+            end,
+            file_data.len()
         )
-        .get_matches();
+        .into());
+    }
+    let file_contents = &file_data[start..end];
+    scan_sizes(file_contents, start, options, output)
+}
 
-    let dump_primes = matches.is_present("dump_primes");
-    // TODO: search for all reasonable sizes at the same time
-    let prime_size = matches
-        .value_of("prime_size")
-        .unwrap_or("128")
-        .parse::<usize>()?;
-    let file_name = matches.value_of("FILE").unwrap();
-
-    let null_filter_length = matches
-        .value_of("null_filter_length")
-        .unwrap_or("2")
-        .parse::<usize>()?;
-    let file_contents = read(file_name)?;
-
-    let bar_size = (file_contents.len() - prime_size).try_into().unwrap();
-
-    let pb = ProgressBar::new(bar_size);
-    pb.set_draw_rate(4);
-
-    info!("Finding candidate primes");
-    let probably_primes = file_contents
-        .par_windows(prime_size)
-        // Discard candidates containing too long streaks of 0 bits
-        .progress_with(pb)
-        .filter(|window| {
-            !window
-            .windows(null_filter_length)
-            .any(|sub_window| sub_window.iter().all(|&b| b == 0))
-        })
-        .flat_map(|window| {
-            vec![
-                Integer::from_digits(window, Order::Msf),
-                Integer::from_digits(window, Order::Lsf),
-                ]
-                .into_par_iter()
-            })
-        .filter_map(|number| match number.is_probably_prime(20) {
-            IsPrime::Yes | IsPrime::Probably => Some(number),
-            IsPrime::No => None,
+// Readable ranges of a process' memory, parsed from /proc/<pid>/maps
+struct MemoryRegion {
+    start: usize,
+    end: usize,
+    name: String,
+}
+
+fn read_maps(path: &str) -> Result<Vec<MemoryRegion>, Box<dyn std::error::Error>> {
+    let mut regions = Vec::new();
+    // Every line is: start-end perms offset dev inode [name]
+    for line in read_to_string(path)?.lines() {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() < 5 || !fields[1].starts_with('r') {
+            continue;
+        }
+        let (start, end) = fields[0]
+            .split_once('-')
+            .ok_or_else(|| format!("Invalid mapping: {}", line))?;
+        regions.push(MemoryRegion {
+            start: usize::from_str_radix(start, 16)?,
+            end: usize::from_str_radix(end, 16)?,
+            name: fields[5..].join(" "),
         });
-  
-        
-    let primes: HashSet<_> = probably_primes.collect();
-    //let primes: Vec<_> = Vec::with_capacity(1000);
-    //probably_primes.collect_into(primes);
-    info!("Found {} prime candidates", primes.len());
-    if primes.len() > PRIMES_WARNING_THRESHOLD {
-        warn!("A large number of candidate primes found. This will consume a large amount of memory. Consider lowering the -f parameter")
     }
+    Ok(regions)
+}
 
-    if dump_primes {
-        println!("Primes in file");
-        for prime in primes {
-            println!("{}", prime);
+// Reads every readable region of e.g. /proc/<pid>/mem separately, with offsets as virtual addresses
+#[cfg(unix)]
+fn scan_memory(
+    mem_path: &str,
+    maps_path: &str,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::FileExt;
+
+    let mem = File::open(mem_path)?;
+    for region in read_maps(maps_path)? {
+        if output.done(options) {
+            break;
         }
-    } else {
-        info!("Construct N candidates");
-        let num_primes = primes.len();
-        let pq_tuples = primes.iter().cartesian_product(primes.iter());
-
-        let pb = ProgressBar::new(((num_primes*num_primes)/2).try_into().unwrap());
-        pb.set_draw_rate(4);
-
-        let pqn_tuples: HashMap<_, _> = pq_tuples
-            .filter(|(p, q)| p <= q)
-            .progress_with(pb)
-            .flat_map(|(p, q)| {
-                vec![
-                    (Integer::from(p * q).to_digits::<u8>(Order::Lsf), (p, q)),
-                    (Integer::from(p * q).to_digits::<u8>(Order::Msf), (p, q)),
-                ]
-                .into_iter()
-            })
-            .collect();
+        let mut data = vec![0; region.end - region.start];
+        if let Err(err) = mem.read_exact_at(&mut data, region.start as u64) {
+            warn!(
+                "Skipping unreadable region {:#x}-{:#x} {}: {}",
+                region.start, region.end, region.name, err
+            );
+            continue;
+        }
+
+        match options.format {
+            OutputFormat::Jsonl => {
+                let record = Record::Region {
+                    start: region.start,
+                    end: region.end,
+                    name: region.name,
+                };
+                writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+            }
+            _ => writeln!(
+                output.results,
+                "Region: {:#x}-{:#x} {}",
+                region.start, region.end, region.name
+            )?,
+        }
+        if let Err(err) = scan_sizes(&data, region.start, options, output) {
+            warn!("Skipping region {:#x}-{:#x}: {}", region.start, region.end, err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn scan_memory(
+    _mem_path: &str,
+    _maps_path: &str,
+    _options: &Options,
+    _output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--maps is only supported on Unix".into())
+}
 
-        //let valid_primes = finder_sliding_window(&pqn_tuples, &file_contents, prime_size); // Simple
-        //let valid_primes = finder_aho_corasick(&pqn_tuples, &file_contents, prime_size); // Memory expensive
-        let valid_primes = finder_rabin_karp(&pqn_tuples, &file_contents, prime_size); // Slightly faster
+// Scans data starting at offset `start` of the input for every prime size, reading it only once
+fn scan_sizes(
+    file_contents: &[u8],
+    start: usize,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for &prime_size in &options.prime_sizes {
+        if output.done(options) {
+            break;
+        }
+        write_prime_size(prime_size, options, output)?;
+        if options.estimate {
+            write_estimate(file_contents.len(), prime_size, options, output)?;
+        } else {
+            scan_region(file_contents, start, prime_size, options, output)?;
+        }
+    }
+    Ok(())
+}
 
-        println!("Validated primes in file");
-        for (p, q) in valid_primes {
-            let n = Integer::from(*p * *q);
-            println!("P:{} Q:{} N:{}", p, q, n);
+// Labels the results of every prime size when searching for more than one
+fn write_prime_size(
+    prime_size: usize,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.prime_sizes.len() > 1 {
+        match options.format {
+            OutputFormat::Jsonl => {
+                let record = Record::PrimeSize { prime_size };
+                writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+            }
+            _ => writeln!(output.results, "Prime size: {}", prime_size)?,
         }
     }
     Ok(())
 }
+
+// The start and end of every chunk of `length` bytes. Every window holding an N
+// starts in exactly one chunk, and lies entirely within it.
+fn chunk_ranges(
+    length: usize,
+    chunk_size: usize,
+    prime_size: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let overlap = 2 * prime_size - 1;
+    (0..length)
+        .step_by(chunk_size)
+        .map(move |chunk_start| (chunk_start, (chunk_start + chunk_size + overlap).min(length)))
+        .take_while(move |(chunk_start, chunk_end)| chunk_end - chunk_start >= prime_size)
+}
+
+// Whether the chunk at `chunk_start` was scanned before the run resumed from --checkpoint
+fn skip_chunk(chunk_start: usize, prime_size: usize, options: &Options) -> bool {
+    options
+        .checkpoint
+        .as_ref()
+        .is_some_and(|checkpoint| chunk_start < checkpoint.resume_offset(prime_size))
+}
+
+// Records a fully scanned chunk for --checkpoint, unless the scan of it was interrupted
+fn save_checkpoint(
+    prime_size: usize,
+    offset: usize,
+    options: &Options,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match &options.checkpoint {
+        Some(checkpoint) if !interrupted() => checkpoint.save(prime_size, offset),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn is_device(file_name: &str) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    std::fs::metadata(file_name)
+        .map(|metadata| {
+            let file_type = metadata.file_type();
+            file_type.is_block_device() || file_type.is_char_device()
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_device(_file_name: &str) -> bool {
+    false
+}
+
+// Devices can be too large to map or read, so they are read in chunks with positioned reads
+#[cfg(unix)]
+fn scan_device(
+    file_name: &str,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::FileExt;
+
+    let mut file = File::open(file_name)?;
+    // The metadata of a device has no length, but it can be seeked to its end
+    let size = file.seek(SeekFrom::End(0))? as usize;
+    let start = options.start.unwrap_or(0);
+    let end = match options.end {
+        Some(end) => end,
+        None if size > 0 => size,
+        None => return Err(format!("Unknown size of {}, use --end", file_name).into()),
+    };
+    if start > end || (size > 0 && end > size) {
+        return Err(format!(
+            "Invalid scan region {}..{} for device of {} bytes",
+            start, end, size
+        )
+        .into());
+    }
+
+    let chunk_size = options.chunk_size.unwrap_or(DEVICE_CHUNK_SIZE);
+    let mut chunk = Vec::new();
+    for &prime_size in &options.prime_sizes {
+        if output.done(options) {
+            break;
+        }
+        write_prime_size(prime_size, options, output)?;
+        if options.estimate {
+            write_estimate(end - start, prime_size, options, output)?;
+            continue;
+        }
+
+        write_header(&Location::File(start), options, output)?;
+        output.dumped = Some(HashSet::new());
+        for (chunk_start, chunk_end) in chunk_ranges(end - start, chunk_size, prime_size) {
+            if output.done(options) {
+                break;
+            }
+            if skip_chunk(start + chunk_start, prime_size, options) {
+                continue;
+            }
+            info!(
+                "Scanning chunk {:#x}..{:#x}",
+                start + chunk_start,
+                start + chunk_end
+            );
+            chunk.resize(chunk_end - chunk_start, 0);
+            file.read_exact_at(&mut chunk, (start + chunk_start) as u64)?;
+            let location = Location::File(start + chunk_start);
+            scan_data(&chunk, prime_size, &location, options, output)?;
+            save_checkpoint(prime_size, start + chunk_start + chunk_size, options)?;
+        }
+        output.dumped = None;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn scan_device(
+    _file_name: &str,
+    _options: &Options,
+    _output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--device is only supported on Unix".into())
+}
+
+// Prints the rough cost of scanning `length` bytes without testing any candidates
+fn write_estimate(
+    length: usize,
+    prime_size: usize,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if length < prime_size {
+        warn!(
+            "File is smaller than prime size ({} < {} bytes)",
+            length, prime_size
+        );
+        return Ok(());
+    }
+    let windows = length - prime_size + 1;
+    writeln!(output.results, "Windows of {} bytes: {}", prime_size, windows)?;
+
+    // By the prime number theorem, in both byte orders, before any filtering
+    let bits = (prime_size * 8) as f64;
+    let expected = 2.0 * windows as f64 / (bits * std::f64::consts::LN_2);
+    writeln!(
+        output.results,
+        "Expected candidate primes in random data: {:.0}",
+        expected
+    )?;
+
+    let entry_size = 2 * prime_size + PQN_ENTRY_OVERHEAD;
+    let mut counts = vec![PRIMES_WARNING_THRESHOLD];
+    counts.extend(options.max_primes);
+    for num_primes in counts {
+        // Every pair with P < Q, or P <= Q with --allow-square, in both byte orders
+        let pairs = if options.allow_square {
+            num_primes.saturating_add(1)
+        } else {
+            num_primes.saturating_sub(1)
+        };
+        let entries = num_primes.saturating_mul(pairs);
+        writeln!(
+            output.results,
+            "N candidates for {} primes: {} ({:.1} MiB)",
+            num_primes,
+            entries,
+            entries as f64 * entry_size as f64 / (1024.0 * 1024.0)
+        )?;
+    }
+    output.results.flush()?;
+    Ok(())
+}
+
+// Longest bar drawn by --histogram, for the most common bit length
+const HISTOGRAM_WIDTH: usize = 50;
+
+// Prints how many candidate primes there are of every bit length
+fn write_histogram(
+    primes: &HashSet<Integer>,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut counts = BTreeMap::new();
+    for prime in primes {
+        *counts.entry(prime.significant_bits()).or_insert(0) += 1;
+    }
+    let max_count = counts.values().copied().max().unwrap_or(0);
+    for (bits, count) in counts {
+        match options.format {
+            OutputFormat::Text => {
+                // Rare lengths still get a mark, so they stand out from the missing ones
+                let width = (count * HISTOGRAM_WIDTH).div_ceil(max_count);
+                writeln!(output.results, "{:>6} {:>10} {}", bits, count, "#".repeat(width))?
+            }
+            OutputFormat::Csv => writeln!(output.results, "{},{}", bits, count)?,
+            OutputFormat::Jsonl => {
+                let record = Record::BitLength { bits, count };
+                writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+            }
+        }
+    }
+    output.results.flush()?;
+    Ok(())
+}
+
+// Scans the region of the file starting at `start` for primes of a single size
+fn scan_region(
+    file_contents: &[u8],
+    start: usize,
+    prime_size: usize,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.scan_base64 {
+        // All decoded runs are scanned as one buffer, mapped back to their text offsets
+        let runs = base64::find_runs(file_contents, prime_size);
+        info!("Found {} base64 runs", runs.len());
+        let mut decoded = Vec::new();
+        let mut run_offsets = Vec::new();
+        for run in runs {
+            run_offsets.push((decoded.len(), start + run.text_offset));
+            decoded.extend_from_slice(&run.decoded);
+        }
+        let location = Location::Base64(run_offsets);
+        write_header(&location, options, output)?;
+        scan_data(&decoded, prime_size, &location, options, output)
+    } else if let Some(chunk_size) = options.chunk_size {
+        write_header(&file_location(start, options), options, output)?;
+        output.dumped = Some(HashSet::new());
+        for (chunk_start, chunk_end) in chunk_ranges(file_contents.len(), chunk_size, prime_size) {
+            if output.done(options) {
+                break;
+            }
+            if skip_chunk(start + chunk_start, prime_size, options) {
+                continue;
+            }
+            info!(
+                "Scanning chunk {:#x}..{:#x}",
+                start + chunk_start,
+                start + chunk_end
+            );
+            let chunk = &file_contents[chunk_start..chunk_end];
+            let location = file_location(start + chunk_start, options);
+            scan_data(chunk, prime_size, &location, options, output)?;
+            save_checkpoint(prime_size, start + chunk_start + chunk_size, options)?;
+        }
+        output.dumped = None;
+        Ok(())
+    } else {
+        let location = file_location(start, options);
+        write_header(&location, options, output)?;
+        scan_data(file_contents, prime_size, &location, options, output)
+    }
+}
+
+// Written once per file, before the records found by every scan_data call
+fn write_header(
+    location: &Location,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (text, csv) = if options.histogram {
+        ("Bit lengths of candidate primes", "bits,count".to_string())
+    } else if options.dump_primes {
+        ("Primes in file", "prime".to_string())
+    } else if options.dump_validated_primes {
+        ("Primes of validated keys in file", "prime".to_string())
+    } else if options.batch_gcd {
+        ("Values sharing factors", "a,b,gcd".to_string())
+    } else if options.pair_candidates {
+        ("Candidate pairs in file", "p,q,n".to_string())
+    } else if options.invert_primes {
+        (
+            "Bit flipped primes in file",
+            format!("{},prime,composite", location.csv_header()),
+        )
+    } else if !options.certs.is_empty() {
+        ("Compromised certificates", "certificate,n,p,q".to_string())
+    } else if !options.moduli.is_empty() {
+        ("Factors of moduli in file", "n,p,q".to_string())
+    } else if options.benchmark {
+        return Ok(());
+    } else {
+        let private_columns = if options.with_d { ",e,d,dp,dq,qinv" } else { "" };
+        let offsets_column = if options.dedup || options.merge_adjacent {
+            ",offsets"
+        } else {
+            ""
+        };
+        (
+            "Validated primes in file",
+            format!(
+                "{},p,q,n{}{}",
+                location.csv_header(),
+                private_columns,
+                offsets_column
+            ),
+        )
+    };
+    match options.format {
+        OutputFormat::Text => writeln!(output.results, "{}", text)?,
+        OutputFormat::Csv => writeln!(output.results, "{}", csv)?,
+        OutputFormat::Jsonl => {}
+    }
+    Ok(())
+}
+
+fn scan_data(
+    file_contents: &[u8],
+    prime_size: usize,
+    location: &Location,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hex = options.hex;
+    let format = options.format;
+
+    if file_contents.len() < prime_size {
+        return Err(FinderError::FileTooSmall {
+            length: file_contents.len(),
+            prime_size,
+        }
+        .into());
+    }
+    output.summary.bytes += file_contents.len();
+    // The transform keeps every byte at its offset, so the data is transformed once up front
+    let transformed;
+    let file_contents = if options.bit_transform == BitTransform::None {
+        file_contents
+    } else {
+        transformed = options.bit_transform.apply(file_contents);
+        &transformed
+    };
+    if options.key_struct {
+        return write_der_keys(file_contents, prime_size, location, options, output);
+    }
+
+    let candidate_start = Instant::now();
+    // Digit runs replace the byte windows, both for the candidates and the N searched for
+    let numeric_runs = if options.ascii_numeric {
+        find_numeric_runs(file_contents, prime_size)
+    } else {
+        Vec::new()
+    };
+    let mut prime_offsets = None;
+    let mut primes = match &options.primes_in {
+        // Loaded primes have already been tested. Primes from windows with leading zero bytes
+        // are shorter, so every prime is used with the smallest requested size it fits in.
+        Some(loaded) => loaded
+            .iter()
+            .filter(|&prime| {
+                let length = byte_length(prime);
+                options.prime_sizes.iter().find(|&&size| size >= length) == Some(&prime_size)
+            })
+            .cloned()
+            .collect(),
+        None if options.ascii_numeric => {
+            numeric_primes(&numeric_runs, prime_size, options.mr_rounds)
+        }
+        None => {
+            let cache_path = options
+                .cache_dir
+                .as_ref()
+                .map(|cache_dir| cache_path(cache_dir, file_contents, prime_size, options));
+            match &cache_path {
+                Some(path) if path.exists() => {
+                    info!("Loading cached prime candidates from {}", path.display());
+                    read_primes(&path.to_string_lossy(), true)?
+                }
+                _ => {
+                    let find_offsets = if options.two_pass {
+                        find_candidate_prime_offsets_two_pass
+                    } else {
+                        find_candidate_prime_offsets
+                    };
+                    let candidate_options = CandidateOptions {
+                        alignment: options.alignment,
+                        limb_size: options.limb_size,
+                        byte_order: options.byte_order,
+                        null_filter: options.null_filter,
+                        min_entropy: options.min_entropy,
+                        invert_filter: options.invert_filter,
+                        sample: options.sample,
+                        mr_rounds: options.mr_rounds,
+                        msb_set: options.msb_set,
+                    };
+                    let offsets = find_offsets(file_contents, prime_size, &candidate_options);
+                    let mut primes: HashSet<_> = offsets.keys().cloned().collect();
+                    // The offsets are only kept for --max-offset-gap, --truncate and the primes
+                    // dumped from overlapping chunks
+                    let chunked_dump = options.dump_primes && output.dumped.is_some();
+                    if options.max_offset_gap.is_some() || options.truncate || chunked_dump {
+                        prime_offsets = Some(offsets);
+                    }
+                    if options.der_aware {
+                        let der_primes = find_der_primes(file_contents, prime_size, options.mr_rounds);
+                        primes.extend(der_primes);
+                    }
+                    // A search cut short by Ctrl-C has only some of the candidates
+                    match &cache_path {
+                        Some(path) if !interrupted() => write_cache(path, &primes)?,
+                        _ => {}
+                    }
+                    primes
+                }
+            }
+        }
+    };
+    if !options.exclude_primes.is_empty() {
+        let found = primes.len();
+        primes.retain(|prime| !options.exclude_primes.contains(prime));
+        info!("Excluded {} well-known primes", found - primes.len());
+    }
+    if options.strong_only {
+        retain_safe_primes(&mut primes, options.mr_rounds);
+    }
+    if options.prove {
+        retain_proven_primes(&mut primes);
+    }
+    output.summary.candidate_time += candidate_start.elapsed();
+    output.summary.candidates += primes.len();
+    //let primes: Vec<_> = Vec::with_capacity(1000);
+    //probably_primes.collect_into(primes);
+    info!("Found {} prime candidates", primes.len());
+    if primes.len() > PRIMES_WARNING_THRESHOLD {
+        warn!("A large number of candidate primes found. This will consume a large amount of memory. Consider lowering the -f parameter")
+    }
+    if let Some(max_primes) = options.max_primes {
+        if primes.len() > max_primes {
+            if !options.truncate {
+                return Err(format!(
+                    "Found {} prime candidates, more than the maximum of {}",
+                    primes.len(),
+                    max_primes
+                )
+                .into());
+            }
+            warn!("Truncating the prime candidates to {}", max_primes);
+            // The same candidates are kept on every run, the first in the file when the
+            // offsets are known, then the smallest
+            let first_offset = |prime: &Integer| {
+                prime_offsets
+                    .as_ref()
+                    .and_then(|offsets| offsets.get(prime)?.iter().min().copied())
+                    .unwrap_or(usize::MAX)
+            };
+            let mut sorted: Vec<_> = primes.into_iter().collect();
+            sorted.sort_by_cached_key(|prime| (first_offset(prime), prime.clone()));
+            sorted.truncate(max_primes);
+            primes = sorted.into_iter().collect();
+        }
+    }
+
+    let finder_start = Instant::now();
+    if options.histogram {
+        write_histogram(&primes, options, output)?;
+    } else if options.dump_primes {
+        let chunk_start = match location {
+            Location::File(start) | Location::Concat(start, _) => *start,
+            Location::Base64(_) => 0,
+        };
+        // Offsets before this chunk are never found again
+        if let Some(dumped) = &mut output.dumped {
+            dumped.retain(|&(offset, _)| offset >= chunk_start);
+        }
+        for prime in primes {
+            let offsets = prime_offsets.as_ref().and_then(|offsets| offsets.get(&prime));
+            if let (Some(dumped), Some(offsets)) = (&mut output.dumped, offsets) {
+                // A prime in the overlap with the last chunk was already dumped by it
+                let mut new = false;
+                for &offset in offsets {
+                    new |= dumped.insert((chunk_start + offset, prime.clone()));
+                }
+                if !new {
+                    continue;
+                }
+            }
+            write_prime(&prime, prime_size, options, output)?;
+        }
+    } else if options.batch_gcd {
+        let mut values = options.moduli.clone();
+        let mut candidates: Vec<_> = primes.into_iter().collect();
+        candidates.sort_unstable();
+        values.extend(candidates);
+
+        info!("Computing batch GCD of {} values", values.len());
+        let shared = shared_factors(&values);
+        output.summary.validated += shared.len();
+        for (i, j, gcd) in shared {
+            match format {
+                OutputFormat::Text => {
+                    writeln!(output.results, "A:{} B:{} GCD:{}", values[i], values[j], gcd)?
+                }
+                OutputFormat::Csv => {
+                    writeln!(output.results, "{},{},{}", values[i], values[j], gcd)?
+                }
+                OutputFormat::Jsonl => {
+                    let record = Record::SharedFactor {
+                        a: json_integer(&values[i]),
+                        b: json_integer(&values[j]),
+                        gcd: json_integer(&gcd),
+                    };
+                    writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+                }
+            }
+            output.results.flush()?;
+        }
+    } else if options.pair_candidates {
+        let pairs = pair_candidates(&primes, (2 * prime_size * 8) as u32);
+        // Known moduli verify the pairs, instead of them only being plausible
+        let moduli: HashSet<_> = options.moduli.iter().collect();
+        for (p, q) in pairs {
+            let n = Integer::from(p * q);
+            if !moduli.is_empty() && !moduli.contains(&n) {
+                continue;
+            }
+            output.summary.validated += 1;
+
+            let (p_out, q_out, n_out) = (
+                format_integer(p, hex, prime_size, Order::Msf),
+                format_integer(q, hex, prime_size, Order::Msf),
+                format_integer(&n, hex, 2 * prime_size, Order::Msf),
+            );
+            match format {
+                OutputFormat::Text => {
+                    writeln!(output.results, "P:{} Q:{} N:{}", p_out, q_out, n_out)?
+                }
+                OutputFormat::Csv => writeln!(output.results, "{},{},{}", p_out, q_out, n_out)?,
+                OutputFormat::Jsonl => {
+                    let record = Record::Pair {
+                        p: json_integer(p),
+                        q: json_integer(q),
+                        n: json_integer(&n),
+                    };
+                    writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+                }
+            }
+            output.results.flush()?;
+
+            if options.emit_key {
+                for key in private_keys(p, q, options) {
+                    let file = output.file_name.clone();
+                    output.emit_key(&key, options, (&file, None))?
+                }
+            }
+        }
+    } else if options.invert_primes {
+        let flips = find_bit_flips(
+            &primes,
+            file_contents,
+            prime_size,
+            options.byte_order,
+            options.mr_rounds,
+        );
+        for (offset, prime, order) in flips {
+            if output.done(options) {
+                break;
+            }
+            output.summary.validated += 1;
+            let composite = Integer::from_digits(&file_contents[offset..offset + prime_size], order);
+            match format {
+                OutputFormat::Text => writeln!(
+                    output.results,
+                    "{} P:{} C:{}",
+                    location.describe(offset, format),
+                    format_integer(prime, hex, prime_size, order),
+                    format_integer(&composite, hex, prime_size, order)
+                )?,
+                OutputFormat::Csv => writeln!(
+                    output.results,
+                    "{},{},{}",
+                    location.describe(offset, format),
+                    format_integer(prime, hex, prime_size, order),
+                    format_integer(&composite, hex, prime_size, order)
+                )?,
+                OutputFormat::Jsonl => {
+                    let file = location.file(offset).map(String::from);
+                    let (offset, decoded_offset) = location.offsets(offset);
+                    let record = Record::BitFlip {
+                        file,
+                        offset,
+                        decoded_offset,
+                        prime: json_integer(prime),
+                        composite: json_integer(&composite),
+                    };
+                    writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+                }
+            }
+            output.results.flush()?;
+        }
+    } else if !options.certs.is_empty() {
+        // The batch GCD finds every candidate dividing a modulus, and moduli sharing a prime
+        let mut values: Vec<_> = options.certs.iter().map(|(_, n)| n.clone()).collect();
+        let mut candidates: Vec<_> = primes.into_iter().collect();
+        candidates.sort_unstable();
+        values.extend(candidates);
+
+        info!("Computing batch GCD of {} values", values.len());
+        let mut reported = HashSet::new();
+        for (i, j, gcd) in shared_factors(&values) {
+            // The moduli come first, so only the second value can be a candidate
+            for cert in [i, j] {
+                let (name, n) = match options.certs.get(cert) {
+                    Some(cert) => cert,
+                    None => continue,
+                };
+                // A certificate in the bundle twice shares its whole modulus, not a prime
+                if gcd == *n || !reported.insert((cert, gcd.clone())) {
+                    continue;
+                }
+                output.summary.validated += 1;
+
+                let q = Integer::from(n / &gcd);
+                let (n_out, p_out, q_out) = (
+                    format_integer(n, hex, 2 * prime_size, Order::Msf),
+                    format_integer(&gcd, hex, prime_size, Order::Msf),
+                    format_integer(&q, hex, prime_size, Order::Msf),
+                );
+                match format {
+                    OutputFormat::Text => writeln!(
+                        output.results,
+                        "Certificate:{} N:{} P:{} Q:{}",
+                        name, n_out, p_out, q_out
+                    )?,
+                    OutputFormat::Csv => {
+                        writeln!(output.results, "{},{},{},{}", name, n_out, p_out, q_out)?
+                    }
+                    OutputFormat::Jsonl => {
+                        let record = Record::Certificate {
+                            certificate: name.clone(),
+                            n: json_integer(n),
+                            p: json_integer(&gcd),
+                            q: json_integer(&q),
+                        };
+                        writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+                    }
+                }
+                output.results.flush()?;
+            }
+        }
+    } else if !options.moduli.is_empty() {
+        // Known moduli only need to be checked against each candidate, no N construction needed
+        for n in &options.moduli {
+            let mut factors: Vec<_> = try_factor(n, &primes).into_iter().collect();
+
+            if factors.is_empty() && options.montgomery {
+                // Libraries commonly use 64-bit limbs
+                let limb_bits = options.limb_size.unwrap_or(8) as u32 * 8;
+                let found = find_montgomery_factors(file_contents, n, limb_bits);
+                for (offset, p) in &found {
+                    info!("Found {} in Montgomery form modulo {} at {:#x}", p, n, offset);
+                }
+                if let Some((_, p)) = found.into_iter().min_by(|(_, a), (_, b)| a.cmp(b)) {
+                    let q = Integer::from(n / &p);
+                    factors.push((p, q));
+                }
+            }
+
+            if factors.is_empty() {
+                if let Some(max_iterations) = options.pollard_rho {
+                    info!("No candidate divides {}, trying Pollard rho", n);
+                    match pollard_rho::brent(n, max_iterations) {
+                        Some(p) => {
+                            let q = Integer::from(n / &p);
+                            factors.push((p, q));
+                        }
+                        None => warn!(
+                            "Pollard rho timed out after {} iterations on {}",
+                            max_iterations, n
+                        ),
+                    }
+                }
+            }
+
+            output.summary.validated += factors.len();
+            for (p, q) in factors {
+                let (n_out, p_out, q_out) = (
+                    format_integer(n, hex, 2 * prime_size, Order::Msf),
+                    format_integer(&p, hex, prime_size, Order::Msf),
+                    format_integer(&q, hex, prime_size, Order::Msf),
+                );
+                match format {
+                    OutputFormat::Text => {
+                        writeln!(output.results, "N:{} P:{} Q:{}", n_out, p_out, q_out)?
+                    }
+                    OutputFormat::Csv => writeln!(output.results, "{},{},{}", n_out, p_out, q_out)?,
+                    OutputFormat::Jsonl => {
+                        let record = Record::Factor {
+                            n: json_integer(n),
+                            p: json_integer(&p),
+                            q: json_integer(&q),
+                        };
+                        writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+                    }
+                }
+                output.results.flush()?;
+            }
+        }
+    } else if options.trial_division {
+        info!("Dividing every window by the candidates instead of building the N map");
+        let matches =
+            find_composites_by_division(&primes, file_contents, prime_size, options.byte_order);
+        output.summary.validated += matches.len();
+        for (offset, pq) in matches {
+            let m = (offset, &pq, Vec::new());
+            write_match(file_contents, prime_size, location, options, output, m)?;
+        }
+    } else {
+        let mut pqn_tuples = match &options.known_prime {
+            Some(known_prime) => build_known_pqn_map(
+                &primes,
+                known_prime,
+                options.allow_square,
+                options.byte_order,
+            ),
+            None => match (options.max_offset_gap, &prime_offsets) {
+                (Some(max_gap), Some(offsets)) => build_nearby_pqn_map(
+                    &primes,
+                    offsets,
+                    max_gap,
+                    options.allow_square,
+                    options.byte_order,
+                ),
+                _ => build_pqn_map(&primes, options.allow_square, options.byte_order),
+            },
+        };
+        if let Some(prefix) = &options.n_prefix {
+            retain_n_prefix(&mut pqn_tuples, prefix);
+            info!("Kept {} N candidates starting with the prefix", pqn_tuples.len());
+        }
+        if options.benchmark {
+            return benchmark_finders(
+                &pqn_tuples,
+                file_contents,
+                prime_size,
+                options.aho_corasick,
+                output,
+            );
+        }
+
+        if options.ascii_numeric {
+            let matches = numeric_runs.iter().filter_map(|(offset, value)| {
+                pqn_tuples
+                    .get(&value.to_digits::<u8>(Order::Msf))
+                    .map(|pq| (*offset, pq))
+            });
+            for (offset, &(p, q)) in matches {
+                if output.done(options) {
+                    break;
+                }
+                output.summary.validated += 1;
+                let keys = private_keys(p, q, options);
+                let m = (offset, (p, q), Vec::new());
+                if !write_key(prime_size, location, options, output, Order::Msf, keys, m)? {
+                    continue;
+                }
+                let length = file_contents[offset..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_hexdigit())
+                    .count();
+                write_context(file_contents, location, options, output, (offset, length))?;
+            }
+            output.summary.finder_time += finder_start.elapsed();
+            return Ok(());
+        }
+
+        info!("Using {:?} algorithm", options.algorithm);
+        if options.dump_validated_primes {
+            // Only the distinct primes of the validated pairs are printed, smallest first
+            let valid_primes = find_composites(
+                options.algorithm,
+                options.aho_corasick,
+                &pqn_tuples,
+                file_contents,
+                prime_size,
+            );
+            output.summary.validated += valid_primes.len();
+            let validated: BTreeSet<&Integer> = valid_primes
+                .iter()
+                .flat_map(|&(_, &(p, q))| [p, q])
+                .collect();
+            info!(
+                "Found {} validated pairs of {} distinct primes",
+                valid_primes.len(),
+                validated.len()
+            );
+            for prime in validated {
+                write_prime(prime, prime_size, options, output)?;
+            }
+        } else if options.dedup || options.merge_adjacent {
+            let (algorithm, aho_corasick) = (options.algorithm, options.aho_corasick);
+            let valid_primes = if options.stop_after_first {
+                find_first_composite(algorithm, aho_corasick, &pqn_tuples, file_contents, prime_size)
+                    .into_iter()
+                    .collect()
+            } else {
+                find_composites(algorithm, aho_corasick, &pqn_tuples, file_contents, prime_size)
+            };
+
+            let matches = if options.merge_adjacent {
+                // With --merge-adjacent, matches in overlapping windows are printed once as a
+                // cluster, listing the offsets of all of them
+                let found = valid_primes.len();
+                let clusters = merge_adjacent_matches(valid_primes, prime_size);
+                info!("Merged {} matches into {} clusters", found, clusters.len());
+                clusters
+            } else {
+                // With --dedup, a pair found at several offsets is printed once with all its offsets
+                let mut matches: Vec<(usize, &(&Integer, &Integer), Vec<usize>)> = Vec::new();
+                let mut pair_index: HashMap<_, usize> = HashMap::new();
+                for (offset, pq) in valid_primes {
+                    match pair_index.entry(pq) {
+                        Entry::Occupied(entry) => matches[*entry.get()].2.push(offset),
+                        Entry::Vacant(entry) => {
+                            entry.insert(matches.len());
+                            matches.push((offset, pq, vec![offset]));
+                        }
+                    }
+                }
+                matches
+            };
+
+            output.summary.validated += matches.len();
+            for m in matches {
+                write_match(file_contents, prime_size, location, options, output, m)?;
+            }
+        } else {
+            // Prints every pair as soon as it is found, instead of after the whole search
+            let (sender, receiver) = mpsc::channel();
+            let (algorithm, stop_after_first) = (options.algorithm, options.stop_after_first);
+            let aho_corasick = options.aho_corasick;
+            let pqn_tuples = &pqn_tuples;
+            thread::scope(|scope| -> Result<(), Box<dyn std::error::Error>> {
+                scope.spawn(move || {
+                    stream_composites(
+                        algorithm,
+                        aho_corasick,
+                        pqn_tuples,
+                        file_contents,
+                        prime_size,
+                        |m| sender.send(m).is_ok() && !stop_after_first,
+                    )
+                });
+                // Parallel finders can send more than one match before stopping
+                let limit = if stop_after_first { 1 } else { usize::MAX };
+                // Returning early drops the receiver, which stops the finder
+                for (offset, pq) in receiver.iter().take(limit) {
+                    output.summary.validated += 1;
+                    let m = (offset, pq, Vec::new());
+                    write_match(file_contents, prime_size, location, options, output, m)?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    output.summary.finder_time += finder_start.elapsed();
+    Ok(())
+}
+
+// Prints the DER private keys whose primes have this size, as chosen for --primes-in
+fn write_der_keys(
+    file_contents: &[u8],
+    prime_size: usize,
+    location: &Location,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let finder_start = Instant::now();
+    let keys = find_der_keys(file_contents).into_iter().filter(|(_, key)| {
+        let length = byte_length(&key.p).max(byte_length(&key.q));
+        options.prime_sizes.iter().find(|&&size| size >= length) == Some(&prime_size)
+    });
+    for (offset, key) in keys {
+        if output.done(options) {
+            break;
+        }
+        output.summary.validated += 1;
+        let (p, q) = (key.p.clone(), key.q.clone());
+        let m = (offset, (&p, &q), Vec::new());
+        if !write_key(prime_size, location, options, output, Order::Msf, vec![key], m)? {
+            continue;
+        }
+        let length = tlv_length(&file_contents[offset..]).unwrap_or(0);
+        write_context(file_contents, location, options, output, (offset, length))?;
+    }
+    output.summary.finder_time += finder_start.elapsed();
+    Ok(())
+}
+
+// Prints a prime for --dump-primes or --dump-validated-primes, the byte order it was found in is
+// not tracked
+fn write_prime(
+    prime: &Integer,
+    prime_size: usize,
+    options: &Options,
+    output: &mut Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match options.format {
+        OutputFormat::Jsonl => {
+            let record = Record::Prime {
+                prime: json_integer(prime),
+            };
+            writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+        }
+        _ => writeln!(
+            output.results,
+            "{}",
+            format_integer(prime, options.hex, prime_size, Order::Msf)
+        )?,
+    }
+    output.results.flush()?;
+    Ok(())
+}
+
+// Prints a validated pair found at `offset`, along with every offset it was found at for --dedup
+fn write_match(
+    file_contents: &[u8],
+    prime_size: usize,
+    location: &Location,
+    options: &Options,
+    output: &mut Output,
+    (offset, &(p, q), offsets): (usize, &(&Integer, &Integer), Vec<usize>),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let n = Integer::from(p * q);
+    let order = if file_contents[offset..].starts_with(&n.to_digits::<u8>(Order::Msf)) {
+        Order::Msf
+    } else {
+        Order::Lsf
+    };
+
+    let keys = private_keys(p, q, options);
+    let m = (offset, (p, q), offsets);
+    if !write_key(prime_size, location, options, output, order, keys, m)? {
+        return Ok(());
+    }
+    write_context(file_contents, location, options, output, (offset, byte_length(&n)))
+}
+
+// Prints a hexdump of --context bytes before and after the `length` bytes matched at `offset`
+fn write_context(
+    file_contents: &[u8],
+    location: &Location,
+    options: &Options,
+    output: &mut Output,
+    (offset, length): (usize, usize),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let context = match options.context {
+        Some(context) => context,
+        None => return Ok(()),
+    };
+    let start = offset.saturating_sub(context);
+    let end = (offset + length + context).min(file_contents.len());
+    // Lines are numbered by file offset, or by the offset in the decoded run for base64
+    let (file_offset, decoded_offset) = location.offsets(start);
+    let dump = hexdump(&file_contents[start..end], decoded_offset.unwrap_or(file_offset));
+    output.results.write_all(dump.as_bytes())?;
+    output.results.flush()?;
+    Ok(())
+}
+
+// The private keys of P and Q for every exponent invertible modulo phi, when --with-d or
+// --emit-key print them
+fn private_keys(p: &Integer, q: &Integer, options: &Options) -> Vec<RsaKey> {
+    if !options.with_d && !options.emit_key {
+        return Vec::new();
+    }
+    options
+        .exponents
+        .iter()
+        .filter_map(|&e| match RsaKey::from_primes(p, q, e) {
+            Ok(key) => Some(key),
+            Err(err) => {
+                warn!("Skipping private key for P:{} Q:{}, {}", p, q, err);
+                None
+            }
+        })
+        .collect()
+}
+
+// Prints P, Q and N read in `order`, with --with-d once for each of `keys` along with its
+// private parameters. Returns false for a pair --watch printed before, which is skipped.
+fn write_key(
+    prime_size: usize,
+    location: &Location,
+    options: &Options,
+    output: &mut Output,
+    order: Order,
+    keys: Vec<RsaKey>,
+    (offset, (p, q), offsets): (usize, (&Integer, &Integer), Vec<usize>),
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if let Some(reported) = &mut output.reported {
+        if !reported.insert((p.clone(), q.clone())) {
+            return Ok(false);
+        }
+    }
+    let hex = options.hex;
+    let format = options.format;
+    let list_offsets = options.dedup || options.merge_adjacent;
+
+    // Offsets in the file, decoded base64 offsets are not listed
+    let offsets: Vec<_> = offsets
+        .into_iter()
+        .map(|offset| location.offsets(offset).0)
+        .collect();
+    let n = Integer::from(p * q);
+    let (p_out, q_out, n_out) = (
+        format_integer(p, hex, prime_size, order),
+        format_integer(q, hex, prime_size, order),
+        format_integer(&n, hex, 2 * prime_size, order),
+    );
+
+    // A line for every exponent with --with-d, or a single one without private parameters
+    let privates: Vec<_> = if options.with_d && !keys.is_empty() {
+        keys.iter().map(Some).collect()
+    } else {
+        vec![None]
+    };
+
+    for private in privates {
+        match format {
+            OutputFormat::Text => {
+                write!(
+                    output.results,
+                    "{} P:{} Q:{} N:{}",
+                    location.describe(offset, format),
+                    p_out,
+                    q_out,
+                    n_out
+                )?;
+                if let Some(key) = private {
+                    write!(
+                        output.results,
+                        " E:{} D:{} DP:{} DQ:{} QINV:{}",
+                        key.e,
+                        format_integer(&key.d, hex, 2 * prime_size, order),
+                        format_integer(&key.dp, hex, prime_size, order),
+                        format_integer(&key.dq, hex, prime_size, order),
+                        format_integer(&key.qinv, hex, prime_size, order)
+                    )?;
+                }
+                if list_offsets {
+                    let offsets: Vec<_> =
+                        offsets.iter().map(|offset| format!("{:#x}", offset)).collect();
+                    write!(output.results, " offsets={}", offsets.join(","))?;
+                }
+                writeln!(output.results)?
+            }
+            OutputFormat::Csv => {
+                write!(
+                    output.results,
+                    "{},{},{},{}",
+                    location.describe(offset, format),
+                    p_out,
+                    q_out,
+                    n_out
+                )?;
+                match private {
+                    Some(key) => write!(
+                        output.results,
+                        ",{},{},{},{},{}",
+                        key.e,
+                        format_integer(&key.d, hex, 2 * prime_size, order),
+                        format_integer(&key.dp, hex, prime_size, order),
+                        format_integer(&key.dq, hex, prime_size, order),
+                        format_integer(&key.qinv, hex, prime_size, order)
+                    )?,
+                    None if options.with_d => write!(output.results, ",,,,,")?,
+                    None => {}
+                }
+                if list_offsets {
+                    let offsets: Vec<_> =
+                        offsets.iter().map(|offset| offset.to_string()).collect();
+                    write!(output.results, ",{}", offsets.join(";"))?;
+                }
+                writeln!(output.results)?
+            }
+            OutputFormat::Jsonl => {
+                let file = location.file(offset).map(String::from);
+                let (offset, decoded_offset) = location.offsets(offset);
+                let record = Record::Match {
+                    file,
+                    offset,
+                    decoded_offset,
+                    p: json_integer(p),
+                    q: json_integer(q),
+                    n: json_integer(&n),
+                    e: private.map(|key| json_integer(&key.e)),
+                    d: private.map(|key| json_integer(&key.d)),
+                    dp: private.map(|key| json_integer(&key.dp)),
+                    dq: private.map(|key| json_integer(&key.dq)),
+                    qinv: private.map(|key| json_integer(&key.qinv)),
+                    offsets: Some(offsets.clone()).filter(|_| list_offsets),
+                };
+                writeln!(output.results, "{}", serde_json::to_string(&record)?)?
+            }
+        }
+    }
+    output.results.flush()?;
+
+    for key in keys.iter().filter(|_| options.emit_key) {
+        let file = location.file(offset).unwrap_or(&output.file_name).to_string();
+        let offset = location.offsets(offset).0;
+        output.emit_key(key, options, (&file, Some(offset)))?;
+    }
+    Ok(true)
+}
+
+// Parses the value given for the argument `name`, naming the argument if it is invalid
+fn parse_value<T>(name: &str, value: &str) -> Result<T, FinderError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse::<T>().map_err(|err| FinderError::ParseArg {
+        arg: name.replace('_', "-"),
+        value: value.to_string(),
+        message: err.to_string(),
+    })
+}
+
+fn parse_arg<T>(matches: &ArgMatches, name: &str, default: &str) -> Result<T, FinderError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    parse_value(name, matches.value_of(name).unwrap_or(default))
+}
+
+fn parse_optional_arg<T>(matches: &ArgMatches, name: &str) -> Result<Option<T>, FinderError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    matches
+        .value_of(name)
+        .map(|value| parse_value(name, value))
+        .transpose()
+}
+
+fn main() {
+    // The first Ctrl-C stops the search and prints what was found so far, the second exits
+    let handler = ctrlc::set_handler(|| {
+        if interrupted() {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        interrupt();
+    });
+    if let Err(err) = handler {
+        eprintln!("Failed to install the Ctrl-C handler: {}", err);
+    }
+
+    let code = match run() {
+        Ok(_) if interrupted() => EXIT_INTERRUPTED,
+        Ok(0) => EXIT_NOT_FOUND,
+        Ok(_) => EXIT_FOUND,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            EXIT_ERROR
+        }
+    };
+    std::process::exit(code);
+}
+
+// Returns the number of results found
+fn run() -> Result<usize, Box<dyn std::error::Error>> {
+    let run_start = Instant::now();
+    let matches = Command::new("prime-finder")
+        .version("0.2")
+        .about("Finds RSA primes in files")
+        .author("Calle Svensson <calle.svensson@zeta-two.com>")
+        .arg(
+            Arg::new("prime_size")
+                .short('s')
+                .long("prime-size")
+                .value_name("SIZE")
+                .help("Sets the size in bytes of the prime numbers to search for, can be given multiple times or as a comma separated list")
+                .required_unless_present("prime_bits")
+                .multiple_occurrences(true)
+                .use_value_delimiter(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("prime_bits")
+                .long("prime-bits")
+                .value_name("BITS")
+                .help("Sets the size in bits of the prime numbers to search for, can be given multiple times or as a comma separated list")
+                .conflicts_with("prime_size")
+                .multiple_occurrences(true)
+                .use_value_delimiter(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("dump_primes")
+                .short('p')
+                .long("dump-primes")
+                .help("Prints all primes without verifying P*Q"),
+        )
+        .arg(
+            Arg::new("dump_validated_primes")
+                .long("dump-validated-primes")
+                .help("Prints every distinct prime of the validated P and Q once, instead of the pairs")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "histogram",
+                    "batch_gcd",
+                    "pair_candidates",
+                    "invert_primes",
+                    "modulus",
+                    "modulus_file",
+                    "certs",
+                    "key_struct",
+                    "ascii_numeric",
+                    "trial_division",
+                    "benchmark",
+                    "stop_after_first",
+                    "dedup",
+                    "merge_adjacent",
+                    "emit_key",
+                    "with_d",
+                    "context",
+                ]),
+        )
+        .arg(
+            Arg::new("histogram")
+                .long("histogram")
+                .help("Prints how many candidate primes there are of every bit length, without verifying P*Q")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "batch_gcd",
+                    "pair_candidates",
+                    "key_struct",
+                    "benchmark",
+                    "estimate",
+                    "stop_after_first",
+                    "dedup",
+                ]),
+        )
+        .arg(
+            Arg::new("decompress")
+                .long("decompress")
+                .value_name("COMPRESSION")
+                .help("Sets how the input is decompressed, auto detects gzip and zstd")
+                .possible_values(["auto", "gzip", "zstd", "none"])
+                .default_value("auto")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("emit_key")
+                .short('k')
+                .long("emit-key")
+                .help("Prints a PEM encoded RSA private key for each validated P and Q"),
+        )
+        .arg(
+            Arg::new("dedup")
+                .long("dedup")
+                .help("Prints every validated P and Q once, listing all offsets where their N was found"),
+        )
+        .arg(
+            Arg::new("merge_adjacent")
+                .long("merge-adjacent")
+                .help("Prints matches at most SIZE bytes apart once as a cluster, with the P and Q of the first and the offsets of all of them")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "histogram",
+                    "batch_gcd",
+                    "pair_candidates",
+                    "invert_primes",
+                    "key_struct",
+                    "ascii_numeric",
+                    "trial_division",
+                    "benchmark",
+                    "dedup",
+                ]),
+        )
+        .arg(
+            Arg::new("with_d")
+                .long("with-d")
+                .help("Prints the private exponent D and the CRT parameters DP, DQ and QINV for each validated P and Q"),
+        )
+        .arg(
+            Arg::new("exponent")
+                .short('e')
+                .long("exponent")
+                .value_name("E")
+                .help("Sets the public exponent used by --emit-key and --with-d, can be given multiple times or as a comma separated list to try each")
+                .default_value("65537")
+                .multiple_occurrences(true)
+                .use_value_delimiter(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("ssh")
+                .long("ssh")
+                .help("Writes the keys from --emit-key in the OpenSSH private key format instead of PEM")
+                .requires("emit_key"),
+        )
+        .arg(
+            Arg::new("jwk")
+                .long("jwk")
+                .help("Writes the keys from --emit-key as JSON Web Keys, one per line, instead of PEM")
+                .requires("emit_key")
+                .conflicts_with("ssh"),
+        )
+        .arg(
+            Arg::new("key_out")
+                .long("key-out")
+                .value_name("PATH")
+                .help("Writes the keys from --emit-key to a separate file")
+                .requires("emit_key")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("filter_byte")
+                .long("filter-byte")
+                .value_name("HEX")
+                .help("Sets the byte value counted by the null filter in bytes mode")
+                .default_value("00")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("bit_transform")
+                .long("bit-transform")
+                .value_name("TRANSFORM")
+                .help("Reverses the bits or swaps the nibbles of every byte before searching, for layouts storing bytes that way")
+                .possible_values(["none", "reverse-bits", "swap-nibbles"])
+                .default_value("none")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("min_entropy")
+                .long("min-entropy")
+                .value_name("BITS")
+                .help("Filters out any primes with a Shannon entropy below this many bits per byte")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("sample")
+                .long("sample")
+                .value_name("FRACTION")
+                .help("Only tests this fraction of the offsets for candidate primes, chosen by --seed. A key is only found if both primes are tested, so keys can be missed")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .help("Sets the seed choosing the offsets tested by --sample")
+                .default_value("0")
+                .requires("sample")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("invert_filter")
+                .long("invert-filter")
+                .help("Only tests the windows rejected by the null and entropy filters, to see what they discard"),
+        )
+        .arg(
+            Arg::new("two_pass")
+                .long("two-pass")
+                .help("Records the windows passing the null and entropy filters before testing any for primes, which is faster when the filters reject most of a large file")
+                .conflicts_with_all(&["primes_in", "ascii_numeric", "key_struct"]),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("PATH")
+                .help("Writes the found primes to a file instead of stdout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("null_filter_length")
+                .short('f')
+                .long("null-filter-length")
+                .value_name("LENGTH")
+                .help("Filters out any primes with a sequence of null bytes (or bits) this long")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("null_filter_unit")
+                .long("null-filter-unit")
+                .value_name("UNIT")
+                .help("Sets whether --null-filter-length counts null bytes or bits")
+                .possible_values(["bytes", "bits"])
+                .default_value("bytes")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("algorithm")
+                .short('a')
+                .long("algorithm")
+                .value_name("ALGORITHM")
+                .help("Sets the algorithm used to search for composites in the file")
+                .possible_values(["sliding", "aho-corasick", "rabin-karp"])
+                .default_value("rabin-karp")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("msb_set")
+                .long("msb-set")
+                .help("Only keeps primes with the most significant bit set, i.e. exactly SIZE*8 bits long"),
+        )
+        .arg(
+            Arg::new("strong_only")
+                .long("strong-only")
+                .help("Only keeps safe primes P, whose (P-1)/2 is also prime, which cuts the candidates in random data but misses keys of ordinary primes, e.g. from OpenSSL [experimental]"),
+        )
+        .arg(
+            Arg::new("prove")
+                .long("prove")
+                .help("Tests the candidates again with Miller-Rabin to bases that make it deterministic up to 81 bits, larger candidates get a Baillie-PSW test and 76 Miller-Rabin rounds and remain probable primes"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Sets the output format of the primes, pem-bundle only writes the keys of --emit-key sorted by offset")
+                .possible_values(["text", "csv", "jsonl", "pem-bundle"])
+                .default_value("text")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("hex")
+                .long("hex")
+                .help("Prints primes and moduli as zero padded hexadecimal"),
+        )
+        .arg(
+            Arg::new("context")
+                .long("context")
+                .value_name("BYTES")
+                .help("Prints a hexdump of this many bytes before and after every validated N, to see the structure around it")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("ac_impl")
+                .long("ac-impl")
+                .value_name("IMPL")
+                .help("Sets the automaton built by the aho-corasick algorithm, the DFA is faster but uses far more memory")
+                .possible_values(["nfa", "dfa"])
+                .default_value("nfa")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("ac_dense")
+                .long("ac-dense")
+                .value_name("BOOL")
+                .help("Sets whether the aho-corasick NFA uses dense transitions near its start, which is faster but larger")
+                .possible_values(["true", "false"])
+                .default_value("false")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("ac_byte_classes")
+                .long("ac-byte-classes")
+                .value_name("BOOL")
+                .help("Sets whether the aho-corasick DFA maps bytes to equivalence classes, which makes it smaller but slightly slower")
+                .possible_values(["true", "false"])
+                .default_value("true")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("ac_premultiply")
+                .long("ac-premultiply")
+                .value_name("BOOL")
+                .help("Sets whether the aho-corasick DFA premultiplies its state identifiers, which is faster but can make it larger")
+                .possible_values(["true", "false"])
+                .default_value("true")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("alignment")
+                .long("alignment")
+                .value_name("BYTES")
+                .help("Only tests candidate primes at offsets that are a multiple of this, unaligned primes are missed")
+                .default_value("1")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("limb_size")
+                .long("limb-size")
+                .value_name("BYTES")
+                .help("Also reads candidate primes with the bytes reversed within every limb of this size")
+                .possible_values(["4", "8"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("byte_order")
+                .long("byte-order")
+                .value_name("ORDER")
+                .help("Sets the byte orders primes and N are read in, most or least significant byte first")
+                .possible_values(["msf", "lsf", "both"])
+                .default_value("both")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("mr_rounds")
+                .long("mr-rounds")
+                .value_name("ROUNDS")
+                .help("Sets the number of Miller-Rabin rounds used to test candidate primes, which like in GMP run after a Baillie-PSW test and only count above 24, with either backend")
+                .default_value("20")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("modulus")
+                .short('n')
+                .long("modulus")
+                .value_name("N")
+                .help("Searches for prime factors of a known modulus, in decimal or 0x-prefixed hex")
+                .multiple_occurrences(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("modulus_file")
+                .long("modulus-file")
+                .value_name("PATH")
+                .help("Reads known moduli from a file, one per line")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("certs")
+                .long("certs")
+                .value_name("PATH")
+                .help("Reads the RSA keys of a PEM or DER certificate bundle, and reports every certificate a candidate prime or another certificate shares a factor with")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "histogram",
+                    "batch_gcd",
+                    "pair_candidates",
+                    "key_struct",
+                    "known_prime",
+                    "modulus",
+                    "modulus_file",
+                    "benchmark",
+                    "stop_after_first",
+                ])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("cache_dir")
+                .long("cache-dir")
+                .value_name("DIR")
+                .help("Caches the prime candidates in this directory, keyed on the file contents and the parameters used to find them")
+                .conflicts_with("primes_in")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("primes_in")
+                .long("primes-in")
+                .value_name("PATH")
+                .help("Uses the primes printed by a previous --dump-primes run as candidates, instead of searching the file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("exclude_primes")
+                .long("exclude-primes")
+                .value_name("PATH")
+                .help("Removes the primes in this file from the candidates, one per line in decimal or 0x-prefixed hex, e.g. the primes of standard DH groups")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("known_prime")
+                .long("known-prime")
+                .value_name("P")
+                .help("Only searches for moduli of candidate primes and a prime known from elsewhere, in decimal or 0x-prefixed hex")
+                .conflicts_with_all(&["modulus", "modulus_file", "batch_gcd"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("n_prefix")
+                .long("n-prefix")
+                .value_name("HEX")
+                .help("Only searches for N whose most significant bytes are these, in either byte order")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("pollard_rho")
+                .long("pollard-rho")
+                .help("Falls back to Pollard rho on known moduli no candidate prime divides, this can take a long time"),
+        )
+        .arg(
+            Arg::new("montgomery")
+                .long("montgomery")
+                .help("Also searches the file for factors of known moduli stored in Montgomery form, with R set by --limb-size [experimental]"),
+        )
+        .arg(
+            Arg::new("rho_iterations")
+                .long("rho-iterations")
+                .value_name("COUNT")
+                .help("Sets the number of Pollard rho iterations tried on each modulus")
+                .default_value("10000000")
+                .requires("pollard_rho")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("estimate")
+                .long("estimate")
+                .help("Prints the number of windows and the memory needed for N candidates, without scanning"),
+        )
+        .arg(
+            Arg::new("stop_after_first")
+                .long("stop-after-first")
+                .help("Stops at the first validated key")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "batch_gcd",
+                    "modulus",
+                    "modulus_file",
+                    "benchmark",
+                    "estimate",
+                ]),
+        )
+        .arg(
+            Arg::new("benchmark")
+                .long("benchmark")
+                .help("Times all algorithms on the input and checks that they find the same composites"),
+        )
+        .arg(
+            Arg::new("scan_base64")
+                .long("scan-base64")
+                .help("Scans the bytes decoded from base64 text in the file, e.g. PEM files"),
+        )
+        .arg(
+            Arg::new("der_aware")
+                .long("der-aware")
+                .help("Also finds primes encoded as DER INTEGERs, using the length from their header"),
+        )
+        .arg(
+            Arg::new("ascii_numeric")
+                .long("ascii-numeric")
+                .help("Searches for P, Q and N written as decimal or hex digits instead of raw bytes, e.g. in logs or JSON")
+                .conflicts_with_all(&[
+                    "primes_in",
+                    "key_struct",
+                    "scan_base64",
+                    "der_aware",
+                    "benchmark",
+                    "dedup",
+                ]),
+        )
+        .arg(
+            Arg::new("key_struct")
+                .long("key-struct")
+                .help("Finds whole DER RSAPrivateKey structures instead of searching for primes, checking that their values belong together")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "batch_gcd",
+                    "pair_candidates",
+                    "modulus",
+                    "modulus_file",
+                    "known_prime",
+                    "primes_in",
+                    "benchmark",
+                    "dedup",
+                ]),
+        )
+        .arg(
+            Arg::new("pair_candidates")
+                .long("pair-candidates")
+                .help("Prints every pair of candidate primes whose product has the size of N, without searching for N in the file. Known moduli are used to verify the pairs")
+                .conflicts_with_all(&["batch_gcd", "known_prime", "dump_primes"]),
+        )
+        .arg(
+            Arg::new("invert_primes")
+                .long("invert-primes")
+                .help("Prints the composites in the file one bit flip away from a candidate prime, e.g. primes corrupted by fault injection, instead of searching for N")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "histogram",
+                    "batch_gcd",
+                    "pair_candidates",
+                    "modulus",
+                    "modulus_file",
+                    "certs",
+                    "known_prime",
+                    "n_prefix",
+                    "allow_square",
+                    "max_offset_gap",
+                    "ascii_numeric",
+                    "key_struct",
+                    "benchmark",
+                    "dedup",
+                    "emit_key",
+                    "with_d",
+                    "context",
+                    "trial_division",
+                ]),
+        )
+        .arg(
+            Arg::new("trial_division")
+                .long("trial-division")
+                .help("Finds N by dividing every window by the candidate primes instead of with --algorithm, which needs far less memory on many candidates but is slower")
+                .conflicts_with_all(&[
+                    "dump_primes",
+                    "histogram",
+                    "batch_gcd",
+                    "pair_candidates",
+                    "modulus",
+                    "modulus_file",
+                    "certs",
+                    "known_prime",
+                    "n_prefix",
+                    "allow_square",
+                    "ascii_numeric",
+                    "benchmark",
+                    "dedup",
+                    "stop_after_first",
+                ]),
+        )
+        .arg(
+            Arg::new("allow_square")
+                .long("allow-square")
+                .help("Also searches for N = P*P, which a real RSA key never has")
+                .conflicts_with_all(&["pair_candidates", "batch_gcd", "dump_primes"]),
+        )
+        .arg(
+            Arg::new("max_offset_gap")
+                .long("max-offset-gap")
+                .value_name("BYTES")
+                .help("Only pairs candidate primes found at most this many bytes apart, as the primes of a key are usually stored close together")
+                .conflicts_with_all(&[
+                    "primes_in",
+                    "cache_dir",
+                    "der_aware",
+                    "ascii_numeric",
+                    "known_prime",
+                    "trial_division",
+                ])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("batch_gcd")
+                .long("batch-gcd")
+                .help("Finds known moduli and candidate primes sharing a common factor"),
+        )
+        .arg(
+            Arg::new("max_primes")
+                .long("max-primes")
+                .value_name("COUNT")
+                .help("Aborts if more than this many prime candidates are found")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("truncate")
+                .long("truncate")
+                .help("Keeps the first --max-primes candidates instead of aborting")
+                .requires("max_primes"),
+        )
+        .arg(
+            Arg::new("threads")
+                .short('j')
+                .long("threads")
+                .value_name("COUNT")
+                .help("Sets the number of worker threads, 0 uses all cores")
+                .default_value("0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("chunk_size")
+                .long("chunk-size")
+                .value_name("BYTES")
+                .help("Scans the file in chunks of this size to bound memory use, primes in different chunks are never paired")
+                .conflicts_with("scan_base64")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("checkpoint")
+                .long("checkpoint")
+                .value_name("PATH")
+                .help("Records every chunk scanned with --chunk-size in this file, and resumes after the last one when rerun with the same parameters")
+                .requires("chunk_size")
+                .conflicts_with_all(&["recursive", "concat", "maps", "estimate"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("start")
+                .long("start")
+                .value_name("OFFSET")
+                .help("Sets the byte offset in the file where the search starts")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("end")
+                .long("end")
+                .value_name("OFFSET")
+                .help("Sets the byte offset in the file where the search ends (exclusive)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .alias("no-progress")
+                .help("Hides the progress bars"),
+        )
+        .arg(
+            Arg::new("log_level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Sets the level of the log messages, debug lists every candidate prime and trace every N candidate")
+                .possible_values(["error", "warn", "info", "debug", "trace"])
+                .default_value("info")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("progress_rate")
+                .long("progress-rate")
+                .value_name("HZ")
+                .help("Sets the number of times per second progress bars are redrawn")
+                .default_value("4")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("device")
+                .long("device")
+                .help("Reads FILE in chunks with positioned reads like a block device, which is detected automatically")
+                .conflicts_with("scan_base64"),
+        )
+        .arg(
+            Arg::new("maps")
+                .long("maps")
+                .value_name("PATH")
+                .help("Reads the readable regions in this /proc/<pid>/maps file from FILE, e.g. /proc/<pid>/mem, and reports virtual addresses")
+                .conflicts_with_all(&["recursive", "start", "end"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("concat")
+                .long("concat")
+                .help("Scans all FILEs as one file, so primes spanning two of them are also found")
+                .conflicts_with_all(&["maps", "recursive", "device", "scan_base64", "dedup"]),
+        )
+        .arg(
+            Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .help("Scans every file in FILE when it is a directory"),
+        )
+        .arg(
+            Arg::new("parallel_files")
+                .long("parallel-files")
+                .help("Scans the files of --recursive concurrently on the threads of --threads, printing the results of each file at once when it is done")
+                .requires("recursive"),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Rescans FILE whenever it is written to until Ctrl-C, only printing the keys not found by earlier scans")
+                .conflicts_with_all(&[
+                    "recursive",
+                    "concat",
+                    "maps",
+                    "device",
+                    "checkpoint",
+                    "stop_after_first",
+                    "estimate",
+                    "benchmark",
+                ]),
+        )
+        .arg(
+            Arg::new("FILE")
+                .help("Sets the input file or directory to use, or - to read from stdin")
+                .required(true)
+                .multiple_values(true)
+                .index(1),
+        )
+        .get_matches();
+
+    let log_level = LevelFilter::from_str(matches.value_of("log_level").unwrap_or("info"))?;
+    CombinedLogger::init(vec![TermLogger::new(
+        log_level,
+        Config::default(),
+        // Only results go to stdout, so they can be piped
+        TerminalMode::Stderr,
+        ColorChoice::Auto,
+    )])?;
+
+    let threads = parse_arg::<usize>(&matches, "threads", "0")?;
+    ThreadPoolBuilder::new().num_threads(threads).build_global()?;
+    info!("Using {} threads", rayon::current_num_threads());
+
+    let dump_primes = matches.is_present("dump_primes");
+    let emit_key = matches.is_present("emit_key");
+    let with_d = matches.is_present("with_d");
+    let dedup = matches.is_present("dedup");
+    let mut exponents = matches
+        .values_of("exponent")
+        .into_iter()
+        .flatten()
+        .map(|e| {
+            parse_integer(e)?
+                .to_u32()
+                .filter(|&e| e >= 3)
+                .ok_or_else(|| format!("Invalid public exponent {}", e).into())
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+    // 65537 and 0x10001 are the same exponent, and only give one key
+    let mut seen = HashSet::new();
+    exponents.retain(|&e| seen.insert(e));
+    let msb_set = matches.is_present("msb_set");
+    let hex = matches.is_present("hex");
+    let recursive = matches.is_present("recursive");
+    if matches.is_present("quiet") {
+        set_progress_enabled(false);
+    }
+    let progress_rate = parse_arg::<u64>(&matches, "progress_rate", "4")?;
+    if progress_rate == 0 {
+        return Err("The progress rate must be at least 1, use --quiet to hide progress bars".into());
+    }
+    set_progress_rate(progress_rate);
+    let mut prime_sizes = Vec::new();
+    for prime_bits in matches.values_of("prime_bits").into_iter().flatten() {
+        let prime_bits = parse_value::<usize>("prime_bits", prime_bits)?;
+        if prime_bits % 8 != 0 {
+            return Err(format!("Prime size of {} bits is not a multiple of 8", prime_bits).into());
+        }
+        prime_sizes.push(prime_bits / 8);
+    }
+    for prime_size in matches.values_of("prime_size").into_iter().flatten() {
+        let prime_size = parse_value::<usize>("prime_size", prime_size)?;
+        if COMMON_RSA_BITS.contains(&prime_size) {
+            warn!(
+                "--prime-size is in bytes, did you mean -s {} for {} bit primes, or -s {} for {} bit RSA keys?",
+                prime_size / 8,
+                prime_size,
+                prime_size / 16,
+                prime_size
+            );
+        }
+        prime_sizes.push(prime_size);
+    }
+    if let Some(&prime_size) = prime_sizes
+        .iter()
+        .find(|&&prime_size| !(2..=MAX_PRIME_SIZE).contains(&prime_size))
+    {
+        return Err(FinderError::InvalidPrimeSize(prime_size).into());
+    }
+    prime_sizes.sort_unstable();
+    prime_sizes.dedup();
+    info!("Searching for primes of {:?} bytes", prime_sizes);
+    let file_names: Vec<_> = matches.values_of("FILE").unwrap().collect();
+    if file_names.len() > 1 && !matches.is_present("concat") {
+        return Err("Several FILEs can only be scanned together with --concat".into());
+    }
+    let file_name = file_names[0];
+    if matches.is_present("watch") && file_name == "-" {
+        return Err("--watch needs a file, not stdin".into());
+    }
+    let algorithm = parse_arg::<Algorithm>(&matches, "algorithm", "rabin-karp")?;
+    // --format pem-bundle prints nothing but the keys, so the results are formatted as text
+    // and discarded
+    let pem_bundle = matches.value_of("format") == Some("pem-bundle");
+    let format = if pem_bundle {
+        OutputFormat::Text
+    } else {
+        parse_arg::<OutputFormat>(&matches, "format", "text")?
+    };
+    if pem_bundle
+        && (!matches.is_present("emit_key") || matches.is_present("ssh") || matches.is_present("jwk"))
+    {
+        return Err("--format pem-bundle needs --emit-key and can't be used with --ssh or --jwk".into());
+    }
+    let context = parse_optional_arg::<usize>(&matches, "context")?;
+    if context.is_some() && !matches!(format, OutputFormat::Text) {
+        return Err("--context is only supported with --format text".into());
+    }
+
+    let null_filter_length = parse_arg::<usize>(&matches, "null_filter_length", "2")?;
+    if null_filter_length == 0 {
+        return Err("The null filter length must be at least 1".into());
+    }
+    let null_filter_unit = parse_arg::<FilterUnit>(&matches, "null_filter_unit", "bytes")?;
+    let filter_byte = matches.value_of("filter_byte").unwrap_or("00");
+    let filter_byte = u8::from_str_radix(filter_byte.trim_start_matches("0x"), 16)
+        .map_err(|err| format!("Invalid filter byte {}: {}", filter_byte, err))?;
+    let null_filter = NullFilter {
+        length: null_filter_length,
+        unit: null_filter_unit,
+        byte: filter_byte,
+    };
+    let bit_transform = parse_arg::<BitTransform>(&matches, "bit_transform", "none")?;
+    let min_entropy = parse_optional_arg::<f64>(&matches, "min_entropy")?;
+    let mr_rounds = parse_arg::<u32>(&matches, "mr_rounds", "20")?;
+    if mr_rounds == 0 {
+        return Err("The number of Miller-Rabin rounds must be at least 1".into());
+    }
+    info!("Using {} Miller-Rabin rounds", mr_rounds);
+
+    let sample = match matches.value_of("sample") {
+        Some(fraction) => {
+            let fraction = parse_value::<f64>("sample", fraction)?;
+            if !(fraction > 0.0 && fraction <= 1.0) {
+                return Err(format!("The sampled fraction must be in (0, 1], not {}", fraction).into());
+            }
+            let seed = parse_arg::<u64>(&matches, "seed", "0")?;
+            warn!(
+                "Only testing {:.1}% of the offsets, keys are found with a probability of {:.1}%",
+                fraction * 100.0,
+                fraction * fraction * 100.0
+            );
+            Some(Sample { fraction, seed })
+        }
+        None => None,
+    };
+    let aho_corasick = AhoCorasickOptions {
+        dfa: matches.value_of("ac_impl").unwrap_or("nfa") == "dfa",
+        dense: parse_arg::<bool>(&matches, "ac_dense", "false")?,
+        byte_classes: parse_arg::<bool>(&matches, "ac_byte_classes", "true")?,
+        premultiply: parse_arg::<bool>(&matches, "ac_premultiply", "true")?,
+    };
+
+    let alignment = parse_arg::<usize>(&matches, "alignment", "1")?;
+    if alignment == 0 {
+        return Err("The alignment must be at least 1".into());
+    }
+
+    let limb_size = parse_optional_arg::<usize>(&matches, "limb_size")?;
+    if let Some(limb_size) = limb_size {
+        if let Some(prime_size) = prime_sizes.iter().find(|&size| size % limb_size != 0) {
+            return Err(format!(
+                "Prime size of {} bytes is not a multiple of the {} byte limb size",
+                prime_size, limb_size
+            )
+            .into());
+        }
+    }
+
+    let start = parse_optional_arg::<usize>(&matches, "start")?;
+    let end = parse_optional_arg::<usize>(&matches, "end")?;
+    let max_primes = parse_optional_arg::<usize>(&matches, "max_primes")?;
+    let truncate = matches.is_present("truncate");
+    let chunk_size = parse_optional_arg::<usize>(&matches, "chunk_size")?;
+    if chunk_size == Some(0) {
+        return Err("The chunk size must be at least 1".into());
+    }
+    let batch_gcd = matches.is_present("batch_gcd");
+    let pair_candidates = matches.is_present("pair_candidates");
+    let benchmark = matches.is_present("benchmark");
+    let estimate = matches.is_present("estimate");
+    let device = matches.is_present("device");
+    let stop_after_first = matches.is_present("stop_after_first");
+    let scan_base64 = matches.is_present("scan_base64");
+    let der_aware = matches.is_present("der_aware");
+    let compression = parse_arg::<Compression>(&matches, "decompress", "auto")?;
+
+    let mut moduli = matches
+        .values_of("modulus")
+        .into_iter()
+        .flatten()
+        .map(parse_integer)
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(path) = matches.value_of("modulus_file") {
+        for line in read_to_string(path)?.lines().filter(|line| !line.trim().is_empty()) {
+            moduli.push(parse_integer(line)?);
+        }
+    }
+    if !moduli.is_empty() {
+        info!("Searching for factors of {} known moduli", moduli.len());
+    }
+
+    let certs = match matches.value_of("certs") {
+        Some(path) => read_certs(path)?,
+        None => Vec::new(),
+    };
+    let known_prime = matches.value_of("known_prime").map(parse_integer).transpose()?;
+
+    let primes_in = match matches.value_of("primes_in") {
+        Some(path) => {
+            let primes = read_primes(path, hex)?;
+            let max_size = *prime_sizes.last().unwrap();
+            if let Some(prime) = primes.iter().find(|&prime| byte_length(prime) > max_size) {
+                return Err(format!(
+                    "Loaded prime {} is {} bytes long, more than the prime size of {} bytes",
+                    prime,
+                    byte_length(prime),
+                    max_size
+                )
+                .into());
+            }
+            info!("Loaded {} primes from {}", primes.len(), path);
+            Some(primes)
+        }
+        None => None,
+    };
+    // One prime per line, lines starting with # are comments
+    let mut exclude_primes = HashSet::new();
+    if let Some(path) = matches.value_of("exclude_primes") {
+        for line in read_to_string(path)?.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                exclude_primes.insert(parse_integer(line)?);
+            }
+        }
+        info!("Loaded {} primes to exclude from {}", exclude_primes.len(), path);
+    }
+    if matches.is_present("montgomery") && moduli.is_empty() {
+        return Err("--montgomery needs known moduli from --modulus or --modulus-file".into());
+    }
+    let n_prefix = matches.value_of("n_prefix").map(parse_hex_bytes).transpose()?;
+    let cache_dir = match matches.value_of("cache_dir") {
+        Some(path) => {
+            create_dir_all(path)?;
+            Some(PathBuf::from(path))
+        }
+        None => None,
+    };
+    let pollard_rho = if matches.is_present("pollard_rho") {
+        Some(parse_arg::<u64>(&matches, "rho_iterations", "10000000")?)
+    } else {
+        None
+    };
+
+    // Output files are opened before scanning so that errors are reported up front
+    let mut results: Box<dyn Write + Send> = match matches.value_of("output") {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(stdout()),
+    };
+    let mut keys: Option<Box<dyn Write + Send>> = match matches.value_of("key_out") {
+        Some(path) => Some(Box::new(File::create(path)?)),
+        None => None,
+    };
+    // The bundle goes where the results would, unless --key-out is given
+    if pem_bundle {
+        let bundle_out = std::mem::replace(&mut results, Box::new(sink()));
+        keys = Some(keys.unwrap_or(bundle_out));
+    }
+    let mut output = Output {
+        results,
+        keys,
+        summary: Summary::default(),
+        file_name: file_name.to_string(),
+        bundle: pem_bundle.then(Vec::new),
+        reported: matches.is_present("watch").then(HashSet::new),
+        dumped: None,
+    };
+
+    let mut options = Options {
+        dump_primes,
+        histogram: matches.is_present("histogram"),
+        emit_key,
+        with_d,
+        dedup,
+        merge_adjacent: matches.is_present("merge_adjacent"),
+        dump_validated_primes: matches.is_present("dump_validated_primes"),
+        two_pass: matches.is_present("two_pass"),
+        exponents,
+        msb_set,
+        strong_only: matches.is_present("strong_only"),
+        prove: matches.is_present("prove"),
+        hex,
+        context,
+        prime_sizes,
+        alignment,
+        limb_size,
+        byte_order: parse_arg::<ByteOrder>(&matches, "byte_order", "both")?,
+        algorithm,
+        aho_corasick,
+        format,
+        null_filter,
+        min_entropy,
+        invert_filter: matches.is_present("invert_filter"),
+        bit_transform,
+        sample,
+        mr_rounds,
+        start,
+        end,
+        max_primes,
+        truncate,
+        moduli,
+        certs,
+        known_prime,
+        n_prefix,
+        primes_in,
+        exclude_primes,
+        cache_dir,
+        pollard_rho,
+        montgomery: matches.is_present("montgomery"),
+        chunk_size,
+        estimate,
+        device,
+        stop_after_first,
+        batch_gcd,
+        pair_candidates,
+        invert_primes: matches.is_present("invert_primes"),
+        trial_division: matches.is_present("trial_division"),
+        allow_square: matches.is_present("allow_square"),
+        max_offset_gap: parse_optional_arg::<usize>(&matches, "max_offset_gap")?,
+        compression,
+        benchmark,
+        scan_base64,
+        der_aware,
+        ssh: matches.is_present("ssh"),
+        jwk: matches.is_present("jwk"),
+        key_struct: matches.is_present("key_struct"),
+        ascii_numeric: matches.is_present("ascii_numeric"),
+        concat_files: Vec::new(),
+        checkpoint: None,
+    };
+    if let Some(path) = matches.value_of("checkpoint") {
+        let parameters = checkpoint_parameters(file_name, &options);
+        options.checkpoint = Some(Checkpoint::open(Path::new(path), parameters)?);
+    }
+
+    if let Some(maps_path) = matches.value_of("maps") {
+        scan_memory(file_name, maps_path, &options, &mut output)?;
+    } else if matches.is_present("concat") {
+        let (data, concat_files) = read_concat(&file_names, options.compression)?;
+        options.concat_files = concat_files;
+        let start = options.start.unwrap_or(0);
+        let end = options.end.unwrap_or(data.len());
+        if start > end || end > data.len() {
+            return Err(format!(
+                "Invalid scan region {}..{} for files of {} bytes",
+                start,
+                end,
+                data.len()
+            )
+            .into());
+        }
+        scan_sizes(&data[start..end], start, &options, &mut output)?;
+    } else if Path::new(file_name).is_dir() {
+        if !recursive {
+            return Err(format!("{} is a directory, use --recursive to scan it", file_name).into());
+        }
+        let parallel_files = matches.is_present("parallel_files");
+        let mut paths = Vec::new();
+        for entry in WalkDir::new(file_name) {
+            if output.done(&options) {
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    warn!("Skipping unreadable entry: {}", err);
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path().to_string_lossy();
+            if parallel_files {
+                paths.push(path.to_string());
+                continue;
+            }
+            write_file_label(&path, &options, output.results.as_mut())?;
+            if let Err(err) = scan_file(&path, &options, &mut output) {
+                warn!("Skipping {}: {}", path, err);
+            }
+        }
+        if parallel_files {
+            scan_files_parallel(&paths, &options, &mut output)?;
+        }
+    } else {
+        scan_file(file_name, &options, &mut output)?;
+        if matches.is_present("watch") {
+            output.results.flush()?;
+            output.keys().flush()?;
+            watch_file(file_name, &options, &mut output)?;
+        }
+    }
+    if let Some(mut bundle) = output.bundle.take() {
+        bundle.sort_unstable_by(|a, b| (&a.file, a.offset, &a.e).cmp(&(&b.file, b.offset, &b.e)));
+        for entry in bundle {
+            output.keys().write_all(entry.pem.as_bytes())?;
+        }
+    }
+    output.results.flush()?;
+    output.keys().flush()?;
+    // A finished scan has nothing to resume, so a rerun starts over
+    if let Some(checkpoint) = &options.checkpoint {
+        if !interrupted() && checkpoint.path.exists() {
+            remove_file(&checkpoint.path)?;
+        }
+    }
+
+    let summary = &output.summary;
+    let elapsed = run_start.elapsed();
+    info!(
+        "Scanned {} bytes in {:.2?} ({:.2} MB/s), found {} candidates and {} validated pairs",
+        summary.bytes,
+        elapsed,
+        summary.bytes as f64 / elapsed.as_secs_f64() / 1e6,
+        summary.candidates,
+        summary.validated
+    );
+    info!(
+        "Spent {:.2?} finding candidates and {:.2?} finding composites",
+        summary.candidate_time, summary.finder_time
+    );
+
+    // Estimates and benchmarks have nothing to find
+    let found = if options.estimate || options.benchmark {
+        1
+    } else if options.dump_primes || options.histogram {
+        summary.candidates
+    } else {
+        summary.validated
+    };
+    if interrupted() {
+        warn!("Interrupted, the results are incomplete");
+    }
+    info!("Found {} results", found);
+    Ok(found)
+}
@@ -0,0 +1,79 @@
+//! Experimental recovery of factors kept in Montgomery form
+//!
+//! Libraries doing modular arithmetic modulo N often store a value `a` as its Montgomery
+//! residue `x = a * R mod N`, where `R = 2^k` and `k` is the bit length of N rounded up to
+//! a whole number of limbs. The bytes in memory are then `x` rather than `a`. Since R is a
+//! power of two it is invertible modulo any odd N, and `a = x * R^-1 mod N` recovers the value.
+//! A factor P of N stored this way is found by converting every modulus sized window and
+//! checking whether the result divides N.
+
+use crate::bigint::{Integer, Order};
+use crate::progress_bar;
+use indicatif::ParallelProgressIterator;
+use log::info;
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSlice};
+use std::convert::TryInto;
+
+/// Returns the exponent k of R = 2^k used for `modulus` with limbs of `limb_bits` bits
+pub fn r_bits(modulus: &Integer, limb_bits: u32) -> u32 {
+    let limbs = modulus.significant_bits().div_ceil(limb_bits);
+    (limbs * limb_bits).max(limb_bits)
+}
+
+/// Returns R^-1 mod `modulus` for R = 2^`r_bits`, or None when `modulus` is even
+pub fn r_inverse(modulus: &Integer, r_bits: u32) -> Option<Integer> {
+    if modulus.is_even() {
+        return None;
+    }
+    (Integer::from(1) << r_bits).invert(modulus).ok()
+}
+
+/// Converts the Montgomery residue `residue` = a*R mod `modulus` back to a
+///
+/// ```
+/// use prime_finder::bigint::Integer;
+/// use prime_finder::montgomery::{from_montgomery, r_bits, r_inverse};
+///
+/// let n = Integer::from(0xfff1) * 0xffef;
+/// let k = r_bits(&n, 64);
+/// let residue = (Integer::from(0xfff1) << k) % &n;
+/// let r_inverse = r_inverse(&n, k).unwrap();
+/// assert_eq!(from_montgomery(&residue, &n, &r_inverse), 0xfff1);
+/// ```
+pub fn from_montgomery(residue: &Integer, modulus: &Integer, r_inverse: &Integer) -> Integer {
+    Integer::from(residue * r_inverse) % modulus
+}
+
+/// Finds nontrivial factors of `modulus` stored in `data` as Montgomery residues modulo it
+///
+/// Every window of the size of `modulus` is read in both byte orders. Returns the offset of
+/// each window whose value is a factor once converted, along with the factor.
+pub fn find_montgomery_factors(
+    data: &[u8],
+    modulus: &Integer,
+    limb_bits: u32,
+) -> Vec<(usize, Integer)> {
+    let r_bits = r_bits(modulus, limb_bits);
+    let r_inverse = match r_inverse(modulus, r_bits) {
+        Some(r_inverse) => r_inverse,
+        None => return Vec::new(),
+    };
+    let window_size = modulus.significant_bits().div_ceil(8) as usize;
+
+    let pb = progress_bar(data.len().saturating_sub(window_size).try_into().unwrap());
+
+    info!("Finding factors in Montgomery form with R = 2^{}", r_bits);
+    data.par_windows(window_size)
+        .enumerate()
+        .progress_with(pb)
+        .flat_map_iter(|(offset, window)| {
+            vec![
+                (offset, Integer::from_digits(window, Order::Msf)),
+                (offset, Integer::from_digits(window, Order::Lsf)),
+            ]
+        })
+        .filter(|(_, residue)| residue < modulus)
+        .map(|(offset, residue)| (offset, from_montgomery(&residue, modulus, &r_inverse)))
+        .filter(|(_, factor)| *factor > 1 && factor != modulus && modulus.is_divisible(factor))
+        .collect()
+}
@@ -0,0 +1,67 @@
+use crate::bigint::{Integer, IsPrime};
+use crate::progress_bar;
+use indicatif::ProgressIterator;
+use log::info;
+use std::{collections::HashSet, convert::TryInto};
+
+/// Finds runs of at least `min_length` decimal or hex digits, e.g. integers in logs or JSON
+///
+/// Returns the offset of every run with its value. Digit only runs are read both as decimal
+/// and as hex, a run with hex letters or a 0x prefix only as hex.
+///
+/// ```
+/// use prime_finder::{bigint::Integer, numeric::find_numeric_runs};
+///
+/// let runs = find_numeric_runs(b"p=65537, n=0xfff1", 4);
+/// assert_eq!(runs[0], (2, Integer::from(65537)));
+/// assert_eq!(runs[1], (2, Integer::from(0x65537)));
+/// assert_eq!(runs[2], (11, Integer::from(0xfff1)));
+/// ```
+pub fn find_numeric_runs(data: &[u8], min_length: usize) -> Vec<(usize, Integer)> {
+    let pb = progress_bar(data.len().try_into().unwrap());
+
+    info!("Finding numeric runs");
+    let mut runs = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let length = data[offset..]
+            .iter()
+            .take_while(|b| b.is_ascii_hexdigit())
+            .count();
+        if length == 0 {
+            offset += 1;
+            continue;
+        }
+        let digits = std::str::from_utf8(&data[offset..offset + length]).unwrap();
+        let prefixed = offset >= 2 && data[offset - 2] == b'0' && data[offset - 1] == b'x';
+        let start = if prefixed { offset - 2 } else { offset };
+        if length >= min_length {
+            if !prefixed && digits.bytes().all(|b| b.is_ascii_digit()) {
+                runs.push((start, Integer::from_str_radix(digits, 10).unwrap()));
+            }
+            runs.push((start, Integer::from_str_radix(digits, 16).unwrap()));
+        }
+        offset += length;
+        pb.set_position(offset.try_into().unwrap());
+    }
+    pb.finish();
+    runs
+}
+
+/// Returns the probable primes of exactly `prime_size` bytes among the values of `runs`
+pub fn numeric_primes(
+    runs: &[(usize, Integer)],
+    prime_size: usize,
+    mr_rounds: u32,
+) -> HashSet<Integer> {
+    runs.iter()
+        .progress_with(progress_bar(runs.len().try_into().unwrap()))
+        .map(|(_, value)| value)
+        .filter(|value| (value.significant_bits() as usize).div_ceil(8) == prime_size)
+        .filter(|value| match value.is_probably_prime(mr_rounds) {
+            IsPrime::Yes | IsPrime::Probably => true,
+            IsPrime::No => false,
+        })
+        .cloned()
+        .collect()
+}
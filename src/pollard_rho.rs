@@ -0,0 +1,74 @@
+use crate::bigint::Integer;
+
+// Number of differences multiplied together between GCD computations
+const BATCH_SIZE: u64 = 128;
+
+// Number of polynomials x^2 + c tried before giving up
+const MAX_ATTEMPTS: u32 = 8;
+
+/// Searches for a nontrivial factor of `n` with Brent's variant of Pollard's rho
+///
+/// Returns None if no factor is found within `max_iterations` steps of the polynomial.
+/// A prime `n` never yields a factor, so it always uses up the whole budget.
+pub fn brent(n: &Integer, max_iterations: u64) -> Option<Integer> {
+    if *n <= 3 {
+        return None;
+    }
+    if n.is_even() {
+        return Some(Integer::from(2));
+    }
+
+    let mut iterations = 0;
+    for c in 1..=MAX_ATTEMPTS {
+        let f = |x: &Integer| (Integer::from(x.square_ref()) + c) % n;
+
+        let mut y = Integer::from(2);
+        let mut x = y.clone();
+        let mut ys = y.clone();
+        let mut product = Integer::from(1);
+        let mut gcd = Integer::from(1);
+        let mut cycle_length = 1;
+
+        while gcd == 1 {
+            x.clone_from(&y);
+            for _ in 0..cycle_length {
+                y = f(&y);
+            }
+
+            let mut k = 0;
+            while k < cycle_length && gcd == 1 {
+                ys.clone_from(&y);
+                let steps = BATCH_SIZE.min(cycle_length - k);
+                for _ in 0..steps {
+                    y = f(&y);
+                    product = product * Integer::from(&x - &y).abs() % n;
+                }
+                gcd = product.gcd_ref(n).into();
+                k += steps;
+            }
+
+            iterations += 2 * cycle_length;
+            if iterations > max_iterations {
+                return None;
+            }
+            cycle_length *= 2;
+        }
+
+        // The batch overshot, step through it one difference at a time
+        if gcd == *n {
+            loop {
+                ys = f(&ys);
+                gcd = Integer::from(&x - &ys).abs().gcd(n);
+                if gcd != 1 {
+                    break;
+                }
+            }
+        }
+
+        // Otherwise the cycle was found modulo every factor at once, retry with another polynomial
+        if gcd != *n {
+            return Some(gcd);
+        }
+    }
+    None
+}
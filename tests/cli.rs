@@ -0,0 +1,95 @@
+mod common;
+
+use common::{fixture, sized_fixture};
+use std::{collections::BTreeSet, fs, path::PathBuf, process::Command};
+
+// A scratch directory for one test, emptied first
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("prime-finder-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Runs the binary and returns the decimal primes it printed
+fn dump_primes(args: &[&str]) -> BTreeSet<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_prime-finder"))
+        .args(args)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_ne!(output.status.code(), Some(2), "{}", stderr);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.is_empty() && line.bytes().all(|b| b.is_ascii_digit()))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn sampled_candidates_are_cached_separately() {
+    let fixture = fixture(128, 23);
+    let dir = scratch_dir("cache");
+    let file = dir.join("data.bin");
+    fs::write(&file, &fixture.data).unwrap();
+    let cache_dir = dir.join("cache");
+    let (file, cache_dir) = (file.to_str().unwrap(), cache_dir.to_str().unwrap());
+    let args = ["-q", "-p", "-f", "16", "-s", "16", "--cache-dir", cache_dir];
+
+    let sampled = dump_primes(&[&args[..], &["--sample", "0.1", file]].concat());
+    let full = dump_primes(&[&args[..], &[file]].concat());
+    assert!(full.contains(&fixture.p.to_string()));
+    assert_ne!(sampled, full);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_smaller_than_prime_size_is_an_error() {
+    let dir = scratch_dir("small");
+    let file = dir.join("data.bin");
+    fs::write(&file, [0xff; 10]).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_prime-finder"))
+        .args(["-q", "-f", "2", "-s", "128", file.to_str().unwrap()])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr);
+    assert!(stderr.contains("File is smaller than prime size (10 < 128 bytes)"), "{}", stderr);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn truncated_candidates_are_reproducible() {
+    let fixture = sized_fixture(128, 29, 4096);
+    let dir = scratch_dir("truncate");
+    let file = dir.join("data.bin");
+    fs::write(&file, &fixture.data).unwrap();
+    let args = ["-q", "-p", "-f", "16", "-s", "16", "--max-primes", "5", "--truncate"];
+    let args = [&args[..], &[file.to_str().unwrap()]].concat();
+
+    let first = dump_primes(&args);
+    assert_eq!(first.len(), 5);
+    for _ in 0..3 {
+        assert_eq!(dump_primes(&args), first);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn prime_in_a_chunk_overlap_is_dumped_once() {
+    let fixture = fixture(128, 31);
+    let dir = scratch_dir("overlap");
+    let file = dir.join("data.bin");
+    fs::write(&file, &fixture.data).unwrap();
+    // P is stored at 53..69, in the first chunk and the overlap of 31 bytes following it
+    let output = Command::new(env!("CARGO_BIN_EXE_prime-finder"))
+        .args(["-q", "-p", "-f", "16", "-s", "16", "--chunk-size", "48"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let p = fixture.p.to_string();
+    assert_eq!(stdout.lines().filter(|&line| line == p).count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
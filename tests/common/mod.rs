@@ -0,0 +1,92 @@
+// Each test crate and the benches use a different part of the fixtures
+#![allow(dead_code)]
+
+use prime_finder::bigint::{Integer, Order};
+
+/// A buffer of random bytes holding the primes of an RSA key and its N in both byte orders
+pub struct Fixture {
+    pub data: Vec<u8>,
+    pub p: Integer,
+    pub q: Integer,
+    pub prime_size: usize,
+    // Offsets of N stored most and least significant byte first
+    pub msf_offset: usize,
+    pub lsf_offset: usize,
+}
+
+// Deterministic xorshift, so a failing fixture can be reproduced from its seed
+struct Random(u64);
+
+impl Random {
+    fn bytes(&mut self, length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|_| {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                self.0 as u8
+            })
+            .collect()
+    }
+
+    // A prime of exactly `bits` bits
+    fn prime(&mut self, bits: u32) -> Integer {
+        loop {
+            let mut number = Integer::from_digits(&self.bytes(bits as usize / 8), Order::Msf);
+            number.set_bit(bits - 1, true);
+            let prime = number.next_prime();
+            if prime.significant_bits() == bits {
+                return prime;
+            }
+        }
+    }
+}
+
+/// Builds a fixture for primes of `prime_bits` bits, a multiple of 8
+pub fn fixture(prime_bits: u32, seed: u64) -> Fixture {
+    let mut random = Random(seed | 1);
+    let prime_size = prime_bits as usize / 8;
+    let p = random.prime(prime_bits);
+    let mut q = random.prime(prime_bits);
+    // N of two primes of the same size can be one byte short, which the finders never match
+    while Integer::from(&p * &q).significant_bits() != 2 * prime_bits || q == p {
+        q = random.prime(prime_bits);
+    }
+    let n = Integer::from(&p * &q);
+
+    let mut data = random.bytes(3 * prime_size + 5);
+    data.extend(p.to_digits::<u8>(Order::Msf));
+    data.extend(random.bytes(prime_size + 3));
+    data.extend(q.to_digits::<u8>(Order::Lsf));
+    data.extend(random.bytes(2 * prime_size + 1));
+    let msf_offset = data.len();
+    data.extend(n.to_digits::<u8>(Order::Msf));
+    data.extend(random.bytes(prime_size + 7));
+    let lsf_offset = data.len();
+    data.extend(n.to_digits::<u8>(Order::Lsf));
+    data.extend(random.bytes(3 * prime_size));
+
+    Fixture {
+        data,
+        p,
+        q,
+        prime_size,
+        msf_offset,
+        lsf_offset,
+    }
+}
+
+/// Returns `length` deterministic random bytes
+#[allow(dead_code)]
+pub fn random_bytes(seed: u64, length: usize) -> Vec<u8> {
+    Random(seed | 1).bytes(length)
+}
+
+/// Builds a fixture padded with random bytes to at least `size` bytes, for the benchmarks
+#[allow(dead_code)]
+pub fn sized_fixture(prime_bits: u32, seed: u64, size: usize) -> Fixture {
+    let mut fixture = fixture(prime_bits, seed);
+    let padding = size.saturating_sub(fixture.data.len());
+    fixture.data.extend(Random(seed.rotate_left(32) | 1).bytes(padding));
+    fixture
+}
@@ -0,0 +1,39 @@
+use prime_finder::{
+    base64::decode_pem, batch_gcd::shared_factors, bigint::Integer, der::find_public_keys,
+};
+
+// A self signed certificate for a 1024 bit key, with the primes of its modulus
+const CERT: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBqjCCAROgAwIBAgIBATANBgkqhkiG9w0BAQsFADAaMRgwFgYDVQQDDA9wcmlt\n\
+ZS1maW5kZXIgY2EwHhcNMjYwMTAxMDAwMDAwWhcNMzYwMTAxMDAwMDAwWjAcMRow\n\
+GAYDVQQDDBFwcmltZS1maW5kZXIgdGVzdDCBnzANBgkqhkiG9w0BAQEFAAOBjQAw\n\
+gYkCgYEAzdfUWg7fn+8HN5/eGMNN8Ad4iT1xNP1dac0k1MDn3e+fGULntHly5ebt\n\
+j1sqV0LLyt98E9liX1XYOPEKMGRcy4gTx2XYWC1VEYcRekIDG+HpeeARjlv1VTWi\n\
+5vYNkLGlZfacRPzfIdH9G/JJHGQ71QTAHM2kpua81LACSYn3CaMCAwEAATANBgkq\n\
+hkiG9w0BAQsFAAOBgQCWEBQaliG9uQkcRfeaP1d8f4dFO0ZEgdOI+aAlJ/COG12g\n\
+6BkFxo9iaIjau6MfFXaXUwUz20807tpxP0pyYq/yENvNR843DmrHqI5mfyIfpz0O\n\
+/IFLyUzxUrOXmpXJvYV6x+JHgOAeBRf/QgUz/lELsBruuV9Hw2XXPXwfjdlrfw==\n\
+-----END CERTIFICATE-----\n";
+const N: &str = "cdd7d45a0edf9fef07379fde18c34df00778893d7134fd5d69cd24d4c0e7ddef9f1942e7b47972e5e6ed8f5b\
+    2a5742cbcadf7c13d9625f55d838f10a30645ccb8813c765d8582d551187117a42031be1e979e0118e5bf555\
+    35a2e6f60d90b1a565f69c44fcdf21d1fd1bf2491c643bd504c01ccda4a6e6bcd4b0024989f709a3";
+const P: &str = "ea9d597a33704be2e9c05bfcd6d487798e7b29b058f864244bba4187b6c3cf003be2e4e7b126109173bbafaa\
+    36fe7f97c076b8eb7744a3030908edcf4bd2e78d";
+
+#[test]
+fn certificate_key_is_found() {
+    let keys = find_public_keys(&decode_pem(CERT.as_bytes())[0]);
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].n, Integer::from_str_radix(N, 16).unwrap());
+    assert_eq!(keys[0].e, 65537);
+    assert_eq!(keys[0].common_name.as_deref(), Some("prime-finder test"));
+}
+
+#[test]
+fn certificate_shares_a_factor_with_its_prime() {
+    let keys = find_public_keys(&decode_pem(CERT.as_bytes())[0]);
+    let p = Integer::from_str_radix(P, 16).unwrap();
+    let shared = shared_factors(&[keys[0].n.clone(), p.clone()]);
+    assert_eq!(shared, vec![(0, 1, p)]);
+}
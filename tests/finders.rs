@@ -0,0 +1,506 @@
+mod common;
+
+use common::{fixture, random_bytes, Fixture};
+use prime_finder::{
+    bigint::{Integer, Order},
+    build_nearby_pqn_map, build_pqn_map,
+    error::FinderError,
+    find_bit_flips, find_candidate_prime_offsets, find_candidate_prime_offsets_two_pass,
+    find_candidate_primes, find_composites, find_composites_by_division, find_first_composite,
+    scan, set_progress_enabled, stream_composites, try_factor, Algorithm, ByteOrder,
+    AhoCorasickOptions, CandidateOptions, FilterUnit, NullFilter, PqnMap, RejectedWindows,
+    ScanOptions,
+};
+use std::{collections::HashSet, sync::Mutex};
+
+const PRIME_BITS: [u32; 3] = [64, 128, 256];
+
+const AC: AhoCorasickOptions = AhoCorasickOptions::DEFAULT;
+
+fn candidates(fixture: &Fixture) -> HashSet<Integer> {
+    set_progress_enabled(false);
+    // Random filler rarely has null streaks this long, so nothing around the key is lost
+    let options = CandidateOptions {
+        null_filter: NullFilter {
+            length: fixture.prime_size,
+            unit: FilterUnit::Bytes,
+            byte: 0,
+        },
+        ..CandidateOptions::default()
+    };
+    find_candidate_primes(&fixture.data, fixture.prime_size, &options)
+}
+
+// The offsets of every match of the fixture's key, sorted
+fn key_offsets(fixture: &Fixture, matches: &[(usize, &(&Integer, &Integer))]) -> Vec<usize> {
+    let (p, q) = if fixture.p < fixture.q {
+        (&fixture.p, &fixture.q)
+    } else {
+        (&fixture.q, &fixture.p)
+    };
+    let mut offsets: Vec<_> = matches
+        .iter()
+        .filter(|(_, &(found_p, found_q))| found_p == p && found_q == q)
+        .map(|&(offset, _)| offset)
+        .collect();
+    offsets.sort_unstable();
+    offsets
+}
+
+#[test]
+fn candidates_include_both_primes() {
+    for (seed, bits) in PRIME_BITS.iter().enumerate() {
+        let fixture = fixture(*bits, seed as u64 + 1);
+        let primes = candidates(&fixture);
+        assert!(primes.contains(&fixture.p), "P missing for {} bits", bits);
+        assert!(primes.contains(&fixture.q), "Q missing for {} bits", bits);
+    }
+}
+
+#[test]
+fn every_algorithm_finds_both_byte_orders() {
+    for (seed, bits) in PRIME_BITS.iter().enumerate() {
+        let fixture = fixture(*bits, seed as u64 + 1);
+        let primes = candidates(&fixture);
+        let pqn_tuples = build_pqn_map(&primes, false, ByteOrder::Both);
+        for algorithm in Algorithm::ALL {
+            let matches =
+                find_composites(algorithm, AC, &pqn_tuples, &fixture.data, fixture.prime_size);
+            assert_eq!(
+                key_offsets(&fixture, &matches),
+                vec![fixture.msf_offset, fixture.lsf_offset],
+                "{:?} with {} bit primes",
+                algorithm,
+                bits
+            );
+        }
+    }
+}
+
+#[test]
+fn pqn_map_encodes_every_pair() {
+    let fixture = fixture(64, 3);
+    let primes = candidates(&fixture);
+    for byte_order in [ByteOrder::Msf, ByteOrder::Lsf, ByteOrder::Both] {
+        // Every N converted to bytes separately in each order
+        let mut expected = HashSet::new();
+        for p in &primes {
+            for q in primes.iter().filter(|&q| p < q) {
+                if byte_order != ByteOrder::Lsf {
+                    expected.insert((Integer::from(p * q).to_digits::<u8>(Order::Msf), p, q));
+                }
+                if byte_order != ByteOrder::Msf {
+                    expected.insert((Integer::from(p * q).to_digits::<u8>(Order::Lsf), p, q));
+                }
+            }
+        }
+        let pqn_tuples = build_pqn_map(&primes, false, byte_order);
+        let built: HashSet<_> = pqn_tuples.into_iter().map(|(n, (p, q))| (n, p, q)).collect();
+        assert_eq!(built, expected, "{:?}", byte_order);
+    }
+}
+
+#[test]
+fn parallel_pqn_map_matches_serial() {
+    let mut primes = HashSet::new();
+    let mut prime = Integer::from(1 << 20);
+    for _ in 0..40 {
+        prime = prime.next_prime();
+        primes.insert(prime.clone());
+    }
+    for allow_square in [false, true] {
+        let mut sorted: Vec<_> = primes.iter().collect();
+        sorted.sort_unstable();
+        let mut serial = PqnMap::new();
+        for (i, &p) in sorted.iter().enumerate() {
+            for &q in &sorted[i..] {
+                if p == q && !allow_square {
+                    continue;
+                }
+                let n = Integer::from(p * q);
+                serial.insert(n.to_digits::<u8>(Order::Msf), (p, q));
+                serial.insert(n.to_digits::<u8>(Order::Lsf), (p, q));
+            }
+        }
+        let parallel = build_pqn_map(&primes, allow_square, ByteOrder::Both);
+        assert_eq!(parallel, serial, "allow_square {}", allow_square);
+    }
+}
+
+#[test]
+fn palindromic_n_is_reported_once() {
+    set_progress_enabled(false);
+    // 11 * 257 is 0x0b0b, the same in both byte orders
+    let primes = HashSet::from([Integer::from(11), Integer::from(257)]);
+    let pqn_tuples = build_pqn_map(&primes, false, ByteOrder::Both);
+    assert_eq!(pqn_tuples.len(), 1);
+
+    let data = [&[0; 10][..], &[0x0b, 0x0b], &[0; 10]].concat();
+    for algorithm in Algorithm::ALL {
+        let matches = find_composites(algorithm, AC, &pqn_tuples, &data, 1);
+        let offsets: Vec<_> = matches.iter().map(|&(offset, _)| offset).collect();
+        assert_eq!(offsets, vec![10], "{:?}", algorithm);
+    }
+    let matches = find_composites_by_division(&primes, &data, 1, ByteOrder::Both);
+    assert_eq!(matches.len(), 1);
+}
+
+#[test]
+fn every_aho_corasick_automaton_finds_the_key() {
+    let fixture = fixture(128, 25);
+    let primes = candidates(&fixture);
+    let pqn_tuples = build_pqn_map(&primes, false, ByteOrder::Both);
+    for dfa in [false, true] {
+        for (dense, byte_classes, premultiply) in [(false, true, true), (true, false, false)] {
+            let options = AhoCorasickOptions {
+                dfa,
+                dense,
+                byte_classes,
+                premultiply,
+            };
+            let matches = find_composites(
+                Algorithm::AhoCorasick,
+                options,
+                &pqn_tuples,
+                &fixture.data,
+                fixture.prime_size,
+            );
+            assert_eq!(
+                key_offsets(&fixture, &matches),
+                vec![fixture.msf_offset, fixture.lsf_offset],
+                "{:?}",
+                options
+            );
+        }
+    }
+}
+
+#[test]
+fn streaming_matches_collecting() {
+    let fixture = fixture(128, 7);
+    let primes = candidates(&fixture);
+    let pqn_tuples = build_pqn_map(&primes, false, ByteOrder::Both);
+    for algorithm in Algorithm::ALL {
+        let streamed = Mutex::new(Vec::new());
+        stream_composites(algorithm, AC, &pqn_tuples, &fixture.data, fixture.prime_size, |m| {
+            streamed.lock().unwrap().push(m);
+            true
+        });
+        let mut streamed = streamed.into_inner().unwrap();
+        streamed.sort_unstable_by_key(|&(offset, _)| offset);
+        let collected =
+            find_composites(algorithm, AC, &pqn_tuples, &fixture.data, fixture.prime_size);
+        assert_eq!(streamed, collected, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn rabin_karp_agrees_with_sliding_window() {
+    for (seed, bits) in PRIME_BITS.iter().enumerate() {
+        let fixture = fixture(*bits, seed as u64 + 41);
+        let primes = candidates(&fixture);
+        let pqn_tuples = build_pqn_map(&primes, true, ByteOrder::Both);
+        let find = |algorithm| {
+            find_composites(algorithm, AC, &pqn_tuples, &fixture.data, fixture.prime_size)
+        };
+        let rabin_karp = find(Algorithm::RabinKarp);
+        assert_eq!(rabin_karp, find(Algorithm::SlidingWindow), "{} bit primes", bits);
+        assert_eq!(key_offsets(&fixture, &rabin_karp).len(), 2);
+    }
+}
+
+// The polynomial hash rolled by the rabin-karp finder
+fn rabin_karp_hash(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |hash, &b| hash.wrapping_mul(0x100000001b3).wrapping_add(b.into()))
+}
+
+#[test]
+fn rabin_karp_skips_hash_collisions() {
+    set_progress_enabled(false);
+    // 1024 bytes following the Thue-Morse sequence and their complement hash the same modulo
+    // 2^64 for any odd base
+    let thue_morse: Vec<bool> = (0..1024u32).map(|i| i.count_ones() % 2 == 1).collect();
+    let n: Vec<u8> = thue_morse.iter().map(|&bit| 0x80 + u8::from(bit)).collect();
+    let collision: Vec<u8> = thue_morse.iter().map(|&bit| 0x81 - u8::from(bit)).collect();
+    assert_eq!(rabin_karp_hash(&n), rabin_karp_hash(&collision));
+
+    let (p, q) = (Integer::from(3), Integer::from(5));
+    let mut pqn_tuples = PqnMap::new();
+    pqn_tuples.insert(n.clone(), (&p, &q));
+    let data = [random_bytes(29, 100), collision, random_bytes(31, 50), n, random_bytes(37, 50)];
+    let matches = find_composites(Algorithm::RabinKarp, AC, &pqn_tuples, &data.concat(), 512);
+    let offsets: Vec<_> = matches.iter().map(|&(offset, _)| offset).collect();
+    assert_eq!(offsets, vec![100 + 1024 + 50]);
+}
+
+#[test]
+fn first_composite_is_the_key() {
+    let fixture = fixture(128, 11);
+    let primes = candidates(&fixture);
+    let pqn_tuples = build_pqn_map(&primes, false, ByteOrder::Both);
+    for algorithm in Algorithm::ALL {
+        let first =
+            find_first_composite(algorithm, AC, &pqn_tuples, &fixture.data, fixture.prime_size);
+        let first: Vec<_> = first.into_iter().collect();
+        assert_eq!(key_offsets(&fixture, &first).len(), 1, "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn scan_rejects_invalid_sizes() {
+    let options = ScanOptions {
+        prime_size: 1,
+        null_filter_length: 2,
+        algorithm: Algorithm::RabinKarp,
+        aho_corasick: AC,
+        mr_rounds: 20,
+    };
+    assert!(matches!(
+        scan(&[0xff; 8], options),
+        Err(FinderError::InvalidPrimeSize(1))
+    ));
+    let options = ScanOptions {
+        prime_size: 16,
+        ..options
+    };
+    assert!(matches!(
+        scan(&[0xff; 8], options),
+        Err(FinderError::FileTooSmall {
+            length: 8,
+            prime_size: 16
+        })
+    ));
+}
+
+#[test]
+fn byte_order_restricts_matches() {
+    let fixture = fixture(128, 5);
+    let primes = candidates(&fixture);
+    for (byte_order, offset) in [
+        (ByteOrder::Msf, fixture.msf_offset),
+        (ByteOrder::Lsf, fixture.lsf_offset),
+    ] {
+        let pqn_tuples = build_pqn_map(&primes, false, byte_order);
+        for algorithm in Algorithm::ALL {
+            let matches =
+                find_composites(algorithm, AC, &pqn_tuples, &fixture.data, fixture.prime_size);
+            assert_eq!(
+                key_offsets(&fixture, &matches),
+                vec![offset],
+                "{:?} with {:?}",
+                algorithm,
+                byte_order
+            );
+        }
+    }
+}
+
+#[test]
+fn nearby_pairs_need_both_primes_within_the_gap() {
+    let fixture = fixture(128, 9);
+    let options = CandidateOptions {
+        null_filter: NullFilter {
+            length: fixture.prime_size,
+            unit: FilterUnit::Bytes,
+            byte: 0,
+        },
+        ..CandidateOptions::default()
+    };
+    let offsets = find_candidate_prime_offsets(&fixture.data, fixture.prime_size, &options);
+    let primes: HashSet<_> = offsets.keys().cloned().collect();
+    // Q is stored 2*SIZE+3 bytes after P
+    for (max_gap, found) in [(3 * fixture.prime_size, 2), (fixture.prime_size, 0)] {
+        let pqn_tuples = build_nearby_pqn_map(&primes, &offsets, max_gap, false, ByteOrder::Both);
+        let matches = find_composites(
+            Algorithm::SlidingWindow,
+            AC,
+            &pqn_tuples,
+            &fixture.data,
+            fixture.prime_size,
+        );
+        assert_eq!(key_offsets(&fixture, &matches).len(), found, "gap of {}", max_gap);
+    }
+}
+
+#[test]
+fn two_pass_finds_the_same_candidates() {
+    let fixture = fixture(128, 15);
+    set_progress_enabled(false);
+    for limb_size in [None, Some(8)] {
+        let options = CandidateOptions {
+            limb_size,
+            null_filter: NullFilter {
+                length: 4,
+                unit: FilterUnit::Bytes,
+                byte: 0,
+            },
+            min_entropy: Some(3.0),
+            ..CandidateOptions::default()
+        };
+        let find = |two_pass: bool| {
+            let find_offsets = if two_pass {
+                find_candidate_prime_offsets_two_pass
+            } else {
+                find_candidate_prime_offsets
+            };
+            find_offsets(&fixture.data, fixture.prime_size, &options)
+        };
+        let single_pass = find(false);
+        assert!(single_pass.contains_key(&fixture.p));
+        assert_eq!(find(true), single_pass, "limb size {:?}", limb_size);
+    }
+}
+
+#[test]
+fn modulus_is_factored_by_the_pool() {
+    let fixture = fixture(128, 21);
+    let n = Integer::from(&fixture.p * &fixture.q);
+    let (p, q) = if fixture.p < fixture.q {
+        (fixture.p.clone(), fixture.q.clone())
+    } else {
+        (fixture.q.clone(), fixture.p.clone())
+    };
+    // N itself divides N, but is no factor
+    let mut primes = candidates(&fixture);
+    primes.insert(n.clone());
+    assert_eq!(try_factor(&n, &primes), Some((p.clone(), q.clone())));
+    primes.remove(&p);
+    assert_eq!(try_factor(&n, &primes), Some((q.clone(), p)));
+    primes.remove(&q);
+    assert_eq!(try_factor(&n, &primes), None);
+}
+
+#[test]
+fn trial_division_finds_both_byte_orders() {
+    for (seed, bits) in PRIME_BITS.iter().enumerate() {
+        let fixture = fixture(*bits, seed as u64 + 1);
+        let primes = candidates(&fixture);
+        let matches =
+            find_composites_by_division(&primes, &fixture.data, fixture.prime_size, ByteOrder::Both);
+        let matches: Vec<_> = matches.iter().map(|(offset, pq)| (*offset, pq)).collect();
+        assert_eq!(
+            key_offsets(&fixture, &matches),
+            vec![fixture.msf_offset, fixture.lsf_offset],
+            "{} bit primes",
+            bits
+        );
+    }
+}
+
+#[test]
+fn bit_flips_find_corrupted_primes() {
+    let fixture = fixture(128, 17);
+    let primes = candidates(&fixture);
+    // A copy of P with its lowest bit cleared, which is even and so never prime
+    let mut data = fixture.data.clone();
+    let offset = data.len();
+    let mut corrupted = fixture.p.to_digits::<u8>(Order::Msf);
+    *corrupted.last_mut().unwrap() ^= 1;
+    data.extend(corrupted);
+    data.extend(random_bytes(19, fixture.prime_size));
+
+    let flips = find_bit_flips(&primes, &data, fixture.prime_size, ByteOrder::Both, 20);
+    assert!(flips.contains(&(offset, &fixture.p, Order::Msf)));
+    let flips = find_bit_flips(&primes, &data, fixture.prime_size, ByteOrder::Lsf, 20);
+    assert!(!flips.iter().any(|&(found, _, _)| found == offset));
+}
+
+#[test]
+fn scan_recovers_the_key() {
+    let fixture = fixture(64, 13);
+    let options = ScanOptions {
+        prime_size: fixture.prime_size,
+        null_filter_length: fixture.prime_size,
+        algorithm: Algorithm::RabinKarp,
+        aho_corasick: AC,
+        mr_rounds: 20,
+    };
+    let result = scan(&fixture.data, options).unwrap();
+    let offsets: Vec<_> = result
+        .validated
+        .iter()
+        .filter(|key| key.n == Integer::from(&fixture.p * &fixture.q))
+        .map(|key| key.offset)
+        .collect();
+    assert_eq!(offsets, vec![Some(fixture.msf_offset), Some(fixture.lsf_offset)]);
+}
+
+// The filter as it was before the single pass, checking every sub-window of the run length
+fn rejects_by_sub_windows(null_filter: &NullFilter, window: &[u8]) -> bool {
+    window
+        .windows(null_filter.length)
+        .any(|sub_window| sub_window.iter().all(|&b| b == null_filter.byte))
+}
+
+#[test]
+fn byte_filter_matches_sub_windows() {
+    // Mostly null bytes, so runs of every length up to the window size occur
+    let data: Vec<u8> = random_bytes(3, 1 << 16)
+        .into_iter()
+        .map(|b| if b < 0xe0 { 0 } else { b })
+        .collect();
+    for length in [1, 2, 3, 5, 8, 16, 33] {
+        for byte in [0x00, 0xcc] {
+            let null_filter = NullFilter {
+                length,
+                unit: FilterUnit::Bytes,
+                byte,
+            };
+            for window in data.windows(32).step_by(7) {
+                let window: Vec<u8> = window
+                    .iter()
+                    .map(|&b| if b == 0 { byte } else { b })
+                    .collect();
+                assert_eq!(
+                    null_filter.rejects(&window),
+                    rejects_by_sub_windows(&null_filter, &window),
+                    "length {} byte {:#x} window {:02x?}",
+                    length,
+                    byte,
+                    window
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rejected_windows_match_the_filter() {
+    for seed in 0..64 {
+        // Null bytes of varying density, so both short and long runs occur
+        let density = 0x40 + (seed as u8 % 4) * 0x30;
+        let data: Vec<u8> = random_bytes(seed + 100, 300 + seed as usize)
+            .into_iter()
+            .map(|b| if b < density { 0xcc } else { b })
+            .collect();
+        for length in [0, 1, 2, 3, 7, 16, 40] {
+            let null_filter = NullFilter {
+                length,
+                unit: FilterUnit::Bytes,
+                byte: 0xcc,
+            };
+            for window_size in [1, 4, 16, 33, 400] {
+                let rejected = RejectedWindows::new(&data, window_size, null_filter).unwrap();
+                for (offset, window) in data.windows(window_size).enumerate() {
+                    assert_eq!(
+                        rejected.contains(offset),
+                        null_filter.rejects(window),
+                        "seed {} length {} window size {} offset {}",
+                        seed,
+                        length,
+                        window_size,
+                        offset
+                    );
+                }
+            }
+        }
+    }
+    let null_filter = NullFilter {
+        length: 8,
+        unit: FilterUnit::Bits,
+        byte: 0,
+    };
+    assert!(RejectedWindows::new(&[0; 16], 8, null_filter).is_none());
+}
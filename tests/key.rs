@@ -0,0 +1,51 @@
+use prime_finder::{base64::decode_pem, bigint::Integer, der::find_der_keys, key::RsaKey};
+use serde_json::Value;
+
+// The private key of RFC 7520 3.4, whose d is the inverse of e modulo phi
+const P: &str = "dd297183f0f04d725c6fad3de51a17ca0402019e519c0bd9967a35ca11ed9d47b1fdfa7b019ffd9d168eec75\
+    fff9215f1907aeb5aa364c38c301653856ea64f2bc3d251d00cd9d0dd9fbee2009abfd60ac986a5e36a4277a\
+    fd53ec8c84b2787c50cb7e9f909a7e1922933844b2b9a7747e8bc4eaef44996c3e9e99bfc6d4ab49";
+const Q: &str = "b8a136761f9c4dfe84445e24e1efe3cbbf067cf61421a532a12489b81ce9dc2b9b937382aacea0ad3f1b47f7\
+    2ed039b5319c169ad76a0f223de47ad47aadcc3f5e6f30c38df251d3799bb69662afc2a5bb6a757953384cd6\
+    267bcf8c8c92e530156a01bf263cf7c117bd10fe85da91c47952a80675f76cc1de9545274b3ba457";
+const JWK: &str = r#"{
+    "kty": "RSA",
+    "n": "n4EPtAOCc9AlkeQHPzHStgAbgs7bTZLwUBZdR8_KuKPEHLd4rHVTeT-O-XV2jRojdNhxJWTDvNd7nqQ0VEiZQHz_AJmSCpMaJMRBSFKrKb2wqVwGU_NsYOYL-QtiWN2lbzcEe6XC0dApr5ydQLrHqkHHig3RBordaZ6Aj-oBHqFEHYpPe7Tpe-OfVfHd1E6cS6M1FZcD1NNLYD5lFHpPI9bTwJlsde3uhGqC0ZCuEHg8lhzwOHrtIQbS0FVbb9k3-tVTU4fg_3L_vniUFAKwuCLqKnS2BYwdq_mzSnbLY7h_qixoR7jig3__kRhuaxwUkRz5iaiQkqgc5gHdrNP5zw",
+    "e": "AQAB",
+    "d": "bWUC9B-EFRIo8kpGfh0ZuyGPvMNKvYWNtB_ikiH9k20eT-O1q_I78eiZkpXxXQ0UTEs2LsNRS-8uJbvQ-A1irkwMSMkK1J3XTGgdrhCku9gRldY7sNA_AKZGh-Q661_42rINLRCe8W-nZ34ui_qOfkLnK9QWDDqpaIsA-bMwWWSDFu2MUBYwkHTMEzLYGqOe04noqeq1hExBTHBOBdkMXiuFhUq1BU6l-DqEiWxqg82sXt2h-LMnT3046AOYJoRioz75tSUQfGCshWTBnP5uDjd18kKhyv07lhfSJdrPdM5Plyl21hsFf4L_mHCuoFau7gdsPfHPxxjVOcOpBrQzwQ",
+    "p": "3Slxg_DwTXJcb6095RoXygQCAZ5RnAvZlno1yhHtnUex_fp7AZ_9nRaO7HX_-SFfGQeutao2TDjDAWU4Vupk8rw9JR0AzZ0N2fvuIAmr_WCsmGpeNqQnev1T7IyEsnh8UMt-n5CafhkikzhEsrmndH6LxOrvRJlsPp6Zv8bUq0k",
+    "q": "uKE2dh-cTf6ERF4k4e_jy78GfPYUIaUyoSSJuBzp3Cubk3OCqs6grT8bR_cu0Dm1MZwWmtdqDyI95HrUeq3MP15vMMON8lHTeZu2lmKvwqW7anV5UzhM1iZ7z4yMkuUwFWoBvyY898EXvRD-hdqRxHlSqAZ192zB3pVFJ0s7pFc",
+    "dp": "B8PVvXkvJrj2L-GYQ7v3y9r6Kw5g9SahXBwsWUzp19TVlgI-YV85q1NIb1rxQtD-IsXXR3-TanevuRPRt5OBOdiMGQp8pbt26gljYfKU_E9xn-RULHz0-ed9E9gXLKD4VGngpz-PfQ_q29pk5xWHoJp009Qf1HvChixRX59ehik",
+    "dq": "CLDmDGduhylc9o7r84rEUVn7pzQ6PF83Y-iBZx5NT-TpnOZKF1pErAMVeKzFEl41DlHHqqBLSM0W1sOFbwTxYWZDm6sI6og5iTbwQGIC3gnJKbi_7k_vJgGHwHxgPaX2PnvP-zyEkDERuf-ry4c_Z11Cq9AqC2yeL6kdKT1cYF8",
+    "qi": "3PiqvXQN0zwMeE-sBvZgi289XP9XCQF3VWqPzMKnIgQp7_Tugo6-NZBKCQsMf3HaEGBjTVJs_jcK8-TRXvaKe-7ZMaQj8VfBdYkssbu0NKDDhjJ-GtiseaDVWt7dcH0cfwxgFUHpQh7FoCrjFJ6h6ZEpMF6xmujs4qMpPz8aaI4"
+}"#;
+
+#[test]
+fn jwk_matches_rfc_7520() {
+    let p = Integer::from_str_radix(P, 16).unwrap();
+    let q = Integer::from_str_radix(Q, 16).unwrap();
+    let key = RsaKey::from_primes(&p, &q, 65537).unwrap();
+    let jwk: Value = serde_json::from_str(&key.to_jwk()).unwrap();
+    let expected: Value = serde_json::from_str(JWK).unwrap();
+    assert_eq!(jwk, expected);
+}
+
+#[test]
+fn pem_round_trips() {
+    let p = Integer::from_str_radix(P, 16).unwrap();
+    let q = Integer::from_str_radix(Q, 16).unwrap();
+    let key = RsaKey::from_primes(&p, &q, 65537).unwrap();
+    let der = decode_pem(key.to_pem().as_bytes());
+    assert_eq!(der, vec![key.to_der()]);
+
+    let keys = find_der_keys(&der[0]);
+    assert_eq!(keys.len(), 1);
+    let (offset, parsed) = &keys[0];
+    assert_eq!(*offset, 0);
+    assert_eq!(parsed.n, key.n);
+    assert_eq!(parsed.e, key.e);
+    assert_eq!(parsed.d, key.d);
+    assert_eq!(parsed.p, p);
+    assert_eq!(parsed.q, q);
+    assert_eq!(parsed.qinv, key.qinv);
+}